@@ -0,0 +1,288 @@
+// animated png, an acTL chunk announces the animation and every frame
+// is an fcTL chunk followed by its image data, in IDAT chunks for the
+// first frame (which is also what viewers without apng support show)
+// and fdAT chunks for the rest
+
+use crate::{ Color, Error, png };
+
+#[cfg(not(feature = "std"))]
+use alloc::vec::Vec;
+
+
+
+/// what happens to a frame's region before the next frame is rendered
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum DisposeOp {
+	/// leave the frame as it is
+	#[default]
+	None = 0,
+	/// clear the region to transparent black
+	Background = 1,
+	/// restore the region to what it was before the frame
+	Previous = 2
+}
+
+/// how a frame is combined with what's already rendered
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum BlendOp {
+	/// replace the region, including its alpha
+	#[default]
+	Source = 0,
+	/// alpha composite the frame over the region
+	Over = 1
+}
+
+
+
+// the contents of an fcTL chunk, minus the sequence number
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub(crate) struct FrameControl {
+	pub width: u32,
+	pub height: u32,
+	pub x: u32,
+	pub y: u32,
+	pub delay: (u16, u16),
+	pub dispose: DisposeOp,
+	pub blend: BlendOp
+}
+
+impl FrameControl {
+
+	pub(crate) fn new (width: u32, height: u32) -> Self {
+		Self { width, height, x: 0, y: 0, delay: (1, 10), dispose: DisposeOp::None, blend: BlendOp::Source }
+	}
+
+	pub(crate) fn data (&self) -> Vec<u8> {
+		[
+			self.width.to_be_bytes().as_slice(),
+			&self.height.to_be_bytes(),
+			&self.x.to_be_bytes(),
+			&self.y.to_be_bytes(),
+			&self.delay.0.to_be_bytes(),
+			&self.delay.1.to_be_bytes(),
+			&[self.dispose as u8, self.blend as u8]
+		].concat()
+	}
+
+}
+
+
+
+/// a frame of an [`Animation`], 8 bit rgba pixels covering a region of
+/// the png
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Frame {
+	pub(crate) control: FrameControl,
+	pub(crate) pixels: Vec<u8>
+}
+
+impl Frame {
+
+	/// a `width` x `height` frame of tightly packed rgba pixels, shown
+	/// for 100ms in the top left corner
+	pub fn new (pixels: Vec<u8>, width: u32, height: u32) -> Self {
+		Self { control: FrameControl::new(width, height), pixels }
+	}
+
+	/// move the frame `x` pixels right and `y` pixels down, the first
+	/// frame always covers the whole png though
+	pub fn offset (mut self, x: u32, y: u32) -> Self {
+		self.control.x = x;
+		self.control.y = y;
+		self
+	}
+
+	/// show the frame for `numerator` / `denominator` seconds, a
+	/// denominator of 0 means 100
+	pub fn delay (mut self, numerator: u16, denominator: u16) -> Self {
+		self.control.delay = (numerator, denominator);
+		self
+	}
+
+	/// set what happens to the frame's region after it's shown
+	pub fn dispose (mut self, dispose: DisposeOp) -> Self {
+		self.control.dispose = dispose;
+		self
+	}
+
+	/// set how the frame is combined with the previous ones
+	pub fn blend (mut self, blend: BlendOp) -> Self {
+		self.control.blend = blend;
+		self
+	}
+
+	// check the frame fits in a `width` x `height` png
+	pub(crate) fn validate (&self, width: u32, height: u32, first: bool) -> Result<(), Error> {
+		let control = &self.control;
+
+		if control.width == 0 || control.height == 0 {
+			return Err(Error::InvalidImage("frame is empty"));
+		}
+		if first && (control.x, control.y, control.width, control.height) != (0, 0, width, height) {
+			return Err(Error::InvalidImage("first frame doesn't cover the whole png"));
+		}
+		if control.x.checked_add(control.width).is_none_or(|right| right > width) || control.y.checked_add(control.height).is_none_or(|bottom| bottom > height) {
+			return Err(Error::InvalidImage("frame doesn't fit in the png"));
+		}
+		if (control.width as usize).checked_mul(control.height as usize).and_then(|pixels| pixels.checked_mul(4)) != Some(self.pixels.len()) {
+			return Err(Error::InvalidImage("pixel buffer doesn't match the frame dimensions"));
+		}
+
+		Ok(())
+	}
+
+}
+
+
+
+/// frames of an animated png, for [`PngOptions::encode_animation`](crate::PngOptions::encode_animation)
+///
+/// ```
+/// use hexpng::{ PngOptions, Animation, Frame };
+///
+/// let red  = [235, 35, 35, 255].repeat(4);
+/// let blue = [35, 35, 235, 255].repeat(4);
+/// let animation = Animation::new()
+///     .frame(Frame::new(red, 2, 2).delay(1, 2))
+///     .frame(Frame::new(blue, 2, 2).delay(1, 2));
+/// let apng = PngOptions::new().dimensions(2, 2).encode_animation(&animation).unwrap();
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Default)]
+pub struct Animation {
+	pub(crate) frames: Vec<Frame>,
+	pub(crate) plays: u32
+}
+
+impl Animation {
+
+	/// an animation without frames that loops forever
+	pub fn new () -> Self {
+		Self::default()
+	}
+
+	/// add a frame after the ones already added
+	pub fn frame (mut self, frame: Frame) -> Self {
+		self.frames.push(frame);
+		self
+	}
+
+	/// play the animation this many times, 0 loops forever
+	pub fn plays (mut self, plays: u32) -> Self {
+		self.plays = plays;
+		self
+	}
+
+}
+
+
+
+// numbers the fcTL and fdAT chunks, which share one sequence starting at 0
+#[derive(Debug, Default)]
+pub(crate) struct Sequence {
+	next: u32
+}
+
+impl Sequence {
+
+	// a complete chunk with the next sequence number in front of `data`
+	pub(crate) fn chunk (&mut self, chunk_type: &[u8], data: &[u8]) -> Result<Vec<u8>, Error> {
+		let chunk = png::chunk(chunk_type, &[&self.next.to_be_bytes(), data].concat())?;
+		self.next += 1;
+		Ok(chunk)
+	}
+
+}
+
+pub(crate) fn actl_data (frames: u32, plays: u32) -> Vec<u8> {
+	[frames.to_be_bytes(), plays.to_be_bytes()].concat()
+}
+
+// `frames` colors going from each of `colors` to the next, the first and
+// last frames being exactly the first and last colors
+//
+// colors are interpolated with premultiplied alpha, so fading from
+// transparent doesn't darken the color
+pub(crate) fn interpolate (colors: &[Color], frames: u32) -> Result<Vec<[u8; 4]>, Error> {
+	if colors.len() < 2 || frames < 2 {
+		return Err(Error::UnsupportedOptions("a transition needs at least 2 colors and 2 frames"));
+	}
+
+	let segments = colors.len() as u64 - 1;
+	let d = frames as u64 - 1;
+
+	let mut steps = Vec::new();
+	steps.try_reserve_exact(frames as usize).map_err(|_| Error::ImageTooLarge)?;
+	steps.extend((0..frames as u64).map(|i| {
+		// which pair of colors the frame is between, and how far along
+		let position = i * segments;
+		let (segment, t) = match position / d {
+			segment if segment == segments => (segment - 1, d),
+			segment => (segment, position % d)
+		};
+		let (from, to) = (colors[segment as usize], colors[segment as usize + 1]);
+
+		let alpha = from.a as u64 * (d - t) + to.a as u64 * t;
+		let channel = |from_channel: u8, to_channel: u8| match alpha {
+			0 => 0,
+			_ => ((from_channel as u64 * from.a as u64 * (d - t) + to_channel as u64 * to.a as u64 * t + alpha / 2) / alpha) as u8
+		};
+
+		[channel(from.r, to.r), channel(from.g, to.g), channel(from.b, to.b), ((alpha + d / 2) / d) as u8]
+	}));
+
+	Ok(steps)
+}
+
+
+
+#[cfg(test)]
+mod tests {
+
+	use super::{ interpolate, FrameControl, Frame, Sequence, DisposeOp, BlendOp };
+	use crate::{ Color, Error };
+
+
+
+	#[test]
+	fn test_interpolate () {
+		let (red, blue) = (Color::rgb(255, 0, 0), Color::rgb(0, 0, 255));
+
+		assert_eq!(interpolate(&[red, blue], 2).unwrap(), [[255, 0, 0, 255], [0, 0, 255, 255]]);
+		assert_eq!(interpolate(&[red, blue], 3).unwrap(), [[255, 0, 0, 255], [128, 0, 128, 255], [0, 0, 255, 255]]);
+		assert_eq!(
+			interpolate(&[red, blue, red], 5).unwrap(),
+			[[255, 0, 0, 255], [128, 0, 128, 255], [0, 0, 255, 255], [128, 0, 128, 255], [255, 0, 0, 255]]
+		);
+
+		// fading in from transparent keeps the color
+		let transparent = Color::new(0, 0, 0, 0);
+		assert_eq!(interpolate(&[transparent, red], 3).unwrap(), [[0, 0, 0, 0], [255, 0, 0, 128], [255, 0, 0, 255]]);
+
+		assert!(matches!(interpolate(&[red], 10), Err(Error::UnsupportedOptions(_))));
+		assert!(matches!(interpolate(&[red, blue], 1), Err(Error::UnsupportedOptions(_))));
+	}
+
+	#[test]
+	fn test_frame_control () {
+		let control = FrameControl { x: 1, y: 2, delay: (3, 4), dispose: DisposeOp::Previous, blend: BlendOp::Over, ..FrameControl::new(5, 6) };
+		assert_eq!(control.data(), [0, 0, 0, 5, 0, 0, 0, 6, 0, 0, 0, 1, 0, 0, 0, 2, 0, 3, 0, 4, 2, 1]);
+
+		let mut sequence = Sequence::default();
+		assert_eq!(sequence.chunk(b"fdAT", b"data").unwrap()[4..16], *b"fdAT\0\0\0\0data");
+		assert_eq!(sequence.chunk(b"fdAT", b"data").unwrap()[4..16], *b"fdAT\0\0\0\x01data");
+	}
+
+	#[test]
+	fn test_validate () {
+		let frame = |width: u32, height: u32| Frame::new([0; 4].repeat((width * height) as usize), width, height);
+
+		assert!(frame(4, 4).validate(4, 4, true).is_ok());
+		assert!(frame(2, 2).offset(2, 2).validate(4, 4, false).is_ok());
+		assert!(frame(2, 2).validate(4, 4, true).is_err());
+		assert!(frame(4, 4).offset(1, 0).validate(4, 4, true).is_err());
+		assert!(frame(2, 2).offset(3, 0).validate(4, 4, false).is_err());
+		assert!(frame(2, 2).offset(u32::MAX, 0).validate(4, 4, false).is_err());
+		assert!(Frame::new([0; 4].to_vec(), 2, 2).validate(2, 2, true).is_err());
+	}
+
+}
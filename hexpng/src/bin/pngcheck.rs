@@ -0,0 +1,39 @@
+// check pngs like pngcheck does, printing a report per file
+
+use std::{ env, fs, process::exit };
+
+use hexpng::validate;
+
+
+
+fn main () {
+
+	let paths = env::args().skip(1).collect::<Vec<_>>();
+	if paths.is_empty() || paths.iter().any(|path| path == "-h" || path == "--help") {
+		eprintln!("usage: pngcheck <file.png>...");
+		exit(2);
+	}
+
+	let mut status = 0;
+	for path in &paths {
+		let png = match fs::read(path) {
+			Ok(png) => png,
+			Err(e) => {
+				eprintln!("{}: {}", path, e);
+				status = 2;
+				continue;
+			}
+		};
+
+		let report = validate(&png);
+		for line in report.to_string().lines() {
+			println!("{}: {}", path, line);
+		}
+		if !report.is_valid() && status == 0 {
+			status = 1;
+		}
+	}
+
+	exit(status);
+
+}
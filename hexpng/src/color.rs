@@ -0,0 +1,220 @@
+use core::{ fmt, str::FromStr };
+
+use crate::css;
+
+
+
+/// an 8 bit per channel rgba color
+///
+/// can be parsed from
+/// - a hexcode with or without the leading `#` in any of the `rgb`,
+///   `rgba`, `rrggbb` or `rrggbbaa` forms
+/// - a css named color like `rebeccapurple` or `transparent`
+/// - the css `rgb()`, `rgba()`, `hsl()`, `hsla()` and `hwb()` functions
+///   with either comma or space separated arguments
+///
+/// everything is case insensitive
+///
+/// ```
+/// use hexpng::Color;
+///
+/// assert_eq!("#EB2323".parse::<Color>(), Ok(Color::new(235, 35, 35, 255)));
+/// assert_eq!("f008".parse::<Color>(), Ok(Color::new(255, 0, 0, 136)));
+/// assert_eq!("rebeccapurple".parse::<Color>(), Ok(Color::rgb(102, 51, 153)));
+/// assert_eq!("rgb(235 35 35 / 50%)".parse::<Color>(), Ok(Color::new(235, 35, 35, 128)));
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct Color {
+	pub r: u8,
+	pub g: u8,
+	pub b: u8,
+	pub a: u8
+}
+
+impl Color {
+
+	/// a color from it's red, green, blue and alpha channels
+	pub const fn new (r: u8, g: u8, b: u8, a: u8) -> Self {
+		Self { r, g, b, a }
+	}
+
+	/// a fully opaque color from it's red, green and blue channels
+	pub const fn rgb (r: u8, g: u8, b: u8) -> Self {
+		Self::new(r, g, b, 255)
+	}
+
+}
+
+impl FromStr for Color {
+	type Err = ParseColorError;
+
+	fn from_str (s: &str) -> Result<Self, Self::Err> {
+		let trimmed = s.trim_start();
+		let offset = s.len() - trimmed.len();
+		let trimmed = trimmed.trim_end();
+
+		if let Some(digits) = trimmed.strip_prefix('#') {
+			parse_hex(digits.as_bytes(), offset + 1)
+		} else if trimmed.contains('(') {
+			css::parse_function(trimmed, offset)
+		} else if trimmed.bytes().all(|b| b.is_ascii_alphabetic()) && !trimmed.bytes().all(|b| b.is_ascii_hexdigit()) {
+			css::parse_named(trimmed, offset)
+		} else {
+			parse_hex(trimmed.as_bytes(), offset)
+		}
+	}
+}
+
+/// formats as an uppercase `#RRGGBB` hexcode, or `#RRGGBBAA` when the
+/// color isn't fully opaque
+impl fmt::Display for Color {
+	fn fmt (&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		write!(f, "#{:02X}{:02X}{:02X}", self.r, self.g, self.b)?;
+		if self.a != 255 {
+			write!(f, "{:02X}", self.a)?;
+		}
+		Ok(())
+	}
+}
+
+// `digits` is a hexcode without the `#`
+fn parse_hex (digits: &[u8], offset: usize) -> Result<Color, ParseColorError> {
+	if digits.is_empty() {
+		return Err(ParseColorError::new(ParseColorErrorKind::Empty, offset));
+	}
+
+	let mut values = [0u8; 8];
+	for (i, &digit) in digits.iter().enumerate().take(values.len()) {
+		values[i] = hex_digit(digit).ok_or(ParseColorError::new(ParseColorErrorKind::InvalidDigit, offset + i))?;
+	}
+
+	let [v0, v1, v2, v3, v4, v5, v6, v7] = values;
+	match digits.len() {
+		3 => Ok(Color::rgb(v0 * 0x11, v1 * 0x11, v2 * 0x11)),
+		4 => Ok(Color::new(v0 * 0x11, v1 * 0x11, v2 * 0x11, v3 * 0x11)),
+		6 => Ok(Color::rgb(v0 << 4 | v1, v2 << 4 | v3, v4 << 4 | v5)),
+		8 => Ok(Color::new(v0 << 4 | v1, v2 << 4 | v3, v4 << 4 | v5, v6 << 4 | v7)),
+		len => Err(ParseColorError::new(ParseColorErrorKind::InvalidLength, offset + len.min(values.len())))
+	}
+}
+
+fn hex_digit (digit: u8) -> Option<u8> {
+	match digit {
+		b'0'..=b'9' => Some(digit - b'0'),
+		b'a'..=b'f' => Some(digit - b'a' + 10),
+		b'A'..=b'F' => Some(digit - b'A' + 10),
+		_ => None
+	}
+}
+
+
+
+/// what went wrong while parsing a [`Color`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ParseColorErrorKind {
+	/// there were no digits to parse
+	Empty,
+	/// a character isn't a hex digit
+	InvalidDigit,
+	/// the number of digits isn't 3, 4, 6 or 8
+	InvalidLength,
+	/// not a css named color
+	UnknownName,
+	/// not one of the supported css color functions
+	UnknownFunction,
+	/// a css color function argument isn't a valid value
+	InvalidArgument,
+	/// a css color function is missing a parenthesis or has the wrong number of arguments
+	InvalidSyntax
+}
+
+/// error returned when a [`Color`] can't be parsed
+///
+/// `position` is the byte offset of the offending character or
+/// argument in the parsed string, for [`ParseColorErrorKind::Empty`],
+/// [`ParseColorErrorKind::InvalidLength`] and
+/// [`ParseColorErrorKind::InvalidSyntax`] it points to where something
+/// was expected or where the string should have ended
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ParseColorError {
+	kind: ParseColorErrorKind,
+	position: usize
+}
+
+impl ParseColorError {
+
+	pub(crate) fn new (kind: ParseColorErrorKind, position: usize) -> Self {
+		Self { kind, position }
+	}
+
+	/// what went wrong
+	pub fn kind (&self) -> ParseColorErrorKind {
+		self.kind
+	}
+
+	/// byte offset into the parsed string where it went wrong
+	pub fn position (&self) -> usize {
+		self.position
+	}
+
+}
+
+impl fmt::Display for ParseColorError {
+	fn fmt (&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self.kind {
+			ParseColorErrorKind::Empty => write!(f, "expected a color at position {}", self.position),
+			ParseColorErrorKind::InvalidDigit => write!(f, "invalid hex digit at position {}", self.position),
+			ParseColorErrorKind::InvalidLength => write!(f, "hexcode should have 3, 4, 6 or 8 digits, unexpected length at position {}", self.position),
+			ParseColorErrorKind::UnknownName => write!(f, "unknown color name at position {}", self.position),
+			ParseColorErrorKind::UnknownFunction => write!(f, "unknown color function at position {}", self.position),
+			ParseColorErrorKind::InvalidArgument => write!(f, "invalid color function argument at position {}", self.position),
+			ParseColorErrorKind::InvalidSyntax => write!(f, "invalid color function syntax at position {}", self.position)
+		}
+	}
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for ParseColorError {}
+
+
+
+#[cfg(test)]
+mod tests {
+
+	use super::{ Color, ParseColorErrorKind };
+
+	#[cfg(not(feature = "std"))]
+	use alloc::format;
+
+
+
+	#[test]
+	fn test_parse_hex () {
+		assert_eq!("#eb2323".parse(), Ok(Color::rgb(235, 35, 35)));
+		assert_eq!("EB2323".parse(), Ok(Color::rgb(235, 35, 35)));
+		assert_eq!("#eB23237f".parse(), Ok(Color::new(235, 35, 35, 127)));
+		assert_eq!("#f80".parse(), Ok(Color::rgb(255, 136, 0)));
+		assert_eq!("F80a".parse(), Ok(Color::new(255, 136, 0, 170)));
+	}
+
+	#[test]
+	fn test_parse_hex_errors () {
+		let error = |s: &str| s.parse::<Color>().map_err(|e| (e.kind(), e.position())).unwrap_err();
+
+		assert_eq!(error(""), (ParseColorErrorKind::Empty, 0));
+		assert_eq!(error("#"), (ParseColorErrorKind::Empty, 1));
+		assert_eq!(error("#eb2g23"), (ParseColorErrorKind::InvalidDigit, 4));
+		assert_eq!(error("eb 323"), (ParseColorErrorKind::InvalidDigit, 2));
+		assert_eq!(error("#é00"), (ParseColorErrorKind::InvalidDigit, 1));
+		assert_eq!(error("#eb"), (ParseColorErrorKind::InvalidLength, 3));
+		assert_eq!(error("#eb232"), (ParseColorErrorKind::InvalidLength, 6));
+		assert_eq!(error("eb2323ff00"), (ParseColorErrorKind::InvalidLength, 8));
+	}
+
+	#[test]
+	fn test_display () {
+		assert_eq!(format!("{}", Color::rgb(235, 35, 35)), "#EB2323");
+		assert_eq!(format!("{}", Color::new(235, 35, 35, 10)), "#EB23230A");
+	}
+
+}
@@ -0,0 +1,219 @@
+// color management, stored in cHRM, gAMA, iCCP, sRGB and cICP chunks
+// that tell viewers how the samples map to actual colors
+
+use crate::{ Error, text, png::Chunk };
+
+#[cfg(not(feature = "std"))]
+use alloc::{ vec, vec::Vec };
+
+
+
+/// how a color managed viewer maps colors that the display can't show
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum RenderingIntent {
+	/// keep the overall look, for photos
+	#[default]
+	Perceptual = 0,
+	/// keep the colors the display can show exactly, for logos and swatches
+	RelativeColorimetric = 1,
+	/// keep colors saturated, for charts
+	Saturation = 2,
+	/// keep the exact colors, including the white point, for proofs
+	AbsoluteColorimetric = 3
+}
+
+
+
+/// cie 1931 x, y chromaticities of the white point and the primaries,
+/// stored with 5 decimal places
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Chromaticities {
+	points: [u32; 8]
+}
+
+impl Chromaticities {
+
+	/// the srgb/bt.709 primaries with a d65 white point
+	pub const SRGB: Self = Self { points: [31270, 32900, 64000, 33000, 30000, 60000, 15000, 6000] };
+
+	/// chromaticities from x, y pairs, clamped to 0 ..= 21474.83647
+	pub fn new (white: (f64, f64), red: (f64, f64), green: (f64, f64), blue: (f64, f64)) -> Self {
+		let mut points = [0; 8];
+		for (point, value) in points.iter_mut().zip([white.0, white.1, red.0, red.1, green.0, green.1, blue.0, blue.1]) {
+			*point = fixed_point(value);
+		}
+		Self { points }
+	}
+
+}
+
+
+
+/// an icc profile embedded in an iCCP chunk
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct IccProfile {
+	name: Vec<u8>,
+	profile: Vec<u8>
+}
+
+impl IccProfile {
+
+	/// the profile `name` follows the same rules as a [`Text`](crate::Text)
+	/// keyword, `profile` is the uncompressed profile which is zlib
+	/// compressed when the png is generated
+	pub fn new (name: &str, profile: &[u8]) -> Result<Self, Error> {
+		Ok(Self {
+			name: text::keyword(name)?,
+			profile: profile.to_vec()
+		})
+	}
+
+}
+
+
+
+/// coding independent code points (itu-t h.273) stored in a cICP chunk,
+/// which take precedence over every other color space chunk
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Cicp {
+	/// the primaries e.g. 1 for bt.709, 9 for bt.2020 or 12 for p3 d65
+	pub colour_primaries: u8,
+	/// the transfer function e.g. 13 for srgb, 16 for pq or 18 for hlg
+	pub transfer_function: u8,
+	/// always 0 for png, which only stores rgb
+	pub matrix_coefficients: u8,
+	/// whether samples use the full range instead of the narrow video range
+	pub full_range: bool
+}
+
+impl Cicp {
+
+	/// srgb
+	pub const SRGB: Self = Self::new(1, 13);
+	/// display p3, the p3 primaries with a d65 white point and the srgb transfer function
+	pub const DISPLAY_P3: Self = Self::new(12, 13);
+	/// hdr bt.2100 with the perceptual quantizer
+	pub const BT2100_PQ: Self = Self::new(9, 16);
+	/// hdr bt.2100 with hybrid log gamma
+	pub const BT2100_HLG: Self = Self::new(9, 18);
+
+	/// full range rgb with these primaries and transfer function
+	pub const fn new (colour_primaries: u8, transfer_function: u8) -> Self {
+		Self { colour_primaries, transfer_function, matrix_coefficients: 0, full_range: true }
+	}
+
+}
+
+
+
+// the color space chunks set on a `PngOptions`
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Default)]
+pub(crate) struct ColorSpace {
+	pub chromaticities: Option<Chromaticities>,
+	pub gamma: Option<u32>,
+	pub icc_profile: Option<IccProfile>,
+	pub srgb: Option<RenderingIntent>,
+	pub cicp: Option<Cicp>
+}
+
+impl ColorSpace {
+
+	// type and data of every chunk, in the order they're stored
+	pub(crate) fn chunks (&self) -> Result<Vec<Chunk>, Error> {
+		let mut chunks = Vec::new();
+
+		if self.icc_profile.is_some() && self.srgb.is_some() {
+			return Err(Error::UnsupportedOptions("a png can't have both an srgb rendering intent and an icc profile"));
+		}
+
+		if let Some(chromaticities) = self.chromaticities {
+			chunks.push((b"cHRM", chromaticities.points.iter().flat_map(|point| point.to_be_bytes()).collect()));
+		}
+		if let Some(gamma) = self.gamma {
+			if gamma == 0 {
+				return Err(Error::InvalidChunk("gamma has to be positive"));
+			}
+			chunks.push((b"gAMA", gamma.to_be_bytes().to_vec()));
+		}
+		if let Some(icc) = &self.icc_profile {
+			let mut data = icc.name.clone();
+			data.extend([0, 0]); // separator and compression method
+			data.extend(crate::deflate::zlib(&icc.profile));
+			chunks.push((b"iCCP", data));
+		}
+		if let Some(intent) = self.srgb {
+			chunks.push((b"sRGB", vec![intent as u8]));
+		}
+		if let Some(cicp) = self.cicp {
+			if cicp.matrix_coefficients != 0 {
+				return Err(Error::InvalidChunk("cicp matrix coefficients have to be 0 for rgb"));
+			}
+			chunks.push((b"cICP", vec![cicp.colour_primaries, cicp.transfer_function, 0, cicp.full_range as u8]));
+		}
+
+		Ok(chunks)
+	}
+
+}
+
+// a value stored as a u32 with 5 decimal places, rounded and clamped to
+// the 2^31 - 1 that png allows for cHRM and gAMA
+pub(crate) fn fixed_point (value: f64) -> u32 {
+	// `as` turns nan into 0
+	(value * 100_000.0 + 0.5).clamp(0.0, i32::MAX as f64) as u32
+}
+
+
+
+#[cfg(test)]
+mod tests {
+
+	use super::{ Chromaticities, ColorSpace, Cicp, IccProfile, RenderingIntent, fixed_point };
+	use crate::Error;
+
+	#[cfg(not(feature = "std"))]
+	use alloc::vec::Vec;
+
+
+
+	#[test]
+	fn test_fixed_point () {
+		assert_eq!(fixed_point(1.0 / 2.2), 45455);
+		assert_eq!(fixed_point(0.3127), 31270);
+		assert_eq!(fixed_point(-1.0), 0);
+		assert_eq!(fixed_point(f64::NAN), 0);
+		assert_eq!(fixed_point(1e10), i32::MAX as u32);
+		assert_eq!(fixed_point(f64::INFINITY), i32::MAX as u32);
+
+		let srgb = Chromaticities::new((0.3127, 0.329), (0.64, 0.33), (0.3, 0.6), (0.15, 0.06));
+		assert_eq!(srgb, Chromaticities::SRGB);
+	}
+
+	#[test]
+	fn test_chunks () {
+		let color_space = ColorSpace {
+			chromaticities: Some(Chromaticities::SRGB),
+			gamma: Some(45455),
+			srgb: Some(RenderingIntent::RelativeColorimetric),
+			cicp: Some(Cicp::DISPLAY_P3),
+			..Default::default()
+		};
+		let chunks = color_space.chunks().unwrap();
+
+		assert_eq!(chunks.iter().map(|(chunk_type, _)| *chunk_type).collect::<Vec<_>>(), [b"cHRM", b"gAMA", b"sRGB", b"cICP"]);
+		assert_eq!(chunks[0].1[..8], [0, 0, 0x7a, 0x26, 0, 0, 0x80, 0x84]);
+		assert_eq!(chunks[1].1, [0, 0, 0xb1, 0x8f]);
+		assert_eq!(chunks[2].1, [1]);
+		assert_eq!(chunks[3].1, [12, 13, 0, 1]);
+	}
+
+	#[test]
+	fn test_invalid_chunks () {
+		let icc_profile = Some(IccProfile::new("Display P3", b"profile").unwrap());
+		assert!(matches!(ColorSpace { icc_profile, srgb: Some(RenderingIntent::Perceptual), ..Default::default() }.chunks(), Err(Error::UnsupportedOptions(_))));
+		assert!(matches!(ColorSpace { gamma: Some(0), ..Default::default() }.chunks(), Err(Error::InvalidChunk(_))));
+		assert!(matches!(ColorSpace { cicp: Some(Cicp { matrix_coefficients: 1, ..Cicp::SRGB }), ..Default::default() }.chunks(), Err(Error::InvalidChunk(_))));
+		assert!(matches!(IccProfile::new(" icc", b""), Err(Error::InvalidChunk(_))));
+	}
+
+}
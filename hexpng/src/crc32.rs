@@ -6,29 +6,79 @@
 // 
 // originally by srijs aka Sam Rijs (dual licensed under MIT and Apache v2.0)
 
+//! the crc32 png chunks, zlib and gzip use, in `no_std` too, as a one
+//! off [`hash`] or an incremental [`Hasher`]
 
 
-#[allow(dead_code)]
+
+/// the crc32 of `buf`
+///
+/// ```
+/// assert_eq!(hexpng::crc32::hash(b"hello world"), 0x0d4a1185);
+/// ```
 pub fn hash (buf: &[u8]) -> u32 {
 	let mut h = Hasher::new();
 	h.append(buf);
 	h.checksum()
 }
 
+/// the crc32 of two buffers one after the other, from the crc32 of each
+/// and the length of the second
+///
+/// ```
+/// use hexpng::crc32::{ combine, hash };
+///
+/// assert_eq!(combine(hash(b"hello"), hash(b" world"), 6), hash(b"hello world"));
+/// ```
+pub fn combine (crc1: u32, crc2: u32, len2: u64) -> u32 {
+	crc32_algorithm::combine(crc1, crc2, len2)
+}
+
 
 
-#[derive(Clone)]
+/// a crc32 (ieee, the one png, zlib and gzip use) of everything appended
+/// to it, slice-by-16 so it goes through bytes quickly
+///
+/// it's also a [`core::hash::Hasher`], for hash maps through
+/// [`BuildHasher`], and a [`std::io::Write`] with the `std` feature
+///
+/// ```
+/// use hexpng::crc32::Hasher;
+///
+/// let mut hasher = Hasher::new();
+/// hasher.append(b"hello");
+/// hasher.append(b" world");
+/// assert_eq!(hasher.checksum(), 0x0d4a1185);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct Hasher {
 	amount: u64,
 	state: u32
 }
 
+/// builds [`Hasher`]s for hash maps and sets
+///
+/// crc32 is an unkeyed 32 bit hash, so keys are easy to collide on purpose
+/// and there's no hashdos protection like the default `RandomState` has,
+/// only use it for keys an attacker doesn't pick
+///
+/// ```
+/// use std::collections::HashMap;
+/// use hexpng::crc32;
+///
+/// let mut map = HashMap::<&str, u32, crc32::BuildHasher>::default();
+/// map.insert("hello", 1);
+/// assert_eq!(map["hello"], 1);
+/// ```
+pub type BuildHasher = core::hash::BuildHasherDefault<Hasher>;
+
 
 
 const DEFAULT_INIT_STATE: u32 = 0;
 
 impl Hasher {
 
+	/// a hasher with nothing appended
 	pub fn new () -> Self {
 		Self {
 			amount: 0,
@@ -36,26 +86,57 @@ impl Hasher {
 		}
 	}
 
-	pub fn seed (&mut self, seed: u32) {
-		self.state = seed;
+	/// continue from the crc32 of `len` earlier bytes, as if they were
+	/// appended, so [`amount`](Self::amount) and [`combine`](Self::combine)
+	/// count them too
+	///
+	/// ```
+	/// use hexpng::crc32::{ Hasher, hash };
+	///
+	/// let mut hasher = Hasher::new();
+	/// hasher.seed(hash(b"hello"), 5);
+	/// hasher.append(b" world");
+	/// assert_eq!(hasher.checksum(), hash(b"hello world"));
+	/// assert_eq!(hasher.amount(), 11);
+	/// ```
+	pub fn seed (&mut self, crc: u32, len: u64) {
+		self.amount = len;
+		self.state = crc;
 	}
 
+	/// add `buf` to the bytes hashed so far
 	pub fn append (&mut self, buf: &[u8]) {
 		self.amount += buf.len() as u64;
 		self.state = crc32_algorithm::update_fast_16(self.state, buf);
 	}
 
+	/// the crc32 of the bytes appended so far
 	pub fn checksum (&self) -> u32 {
 		self.state
 	}
 
-	#[allow(dead_code)]
+	/// how many bytes were appended
+	pub fn amount (&self) -> u64 {
+		self.amount
+	}
+
+	/// forget everything appended
 	pub fn reset (&mut self) {
 		self.amount = 0;
 		self.state = 0;
 	}
 
-	#[allow(dead_code)]
+	/// append the bytes `other` hashed, without having them
+	///
+	/// ```
+	/// use hexpng::crc32::{ Hasher, hash };
+	///
+	/// let (mut hello, mut world) = (Hasher::new(), Hasher::new());
+	/// hello.append(b"hello");
+	/// world.append(b" world");
+	/// hello.combine(&world);
+	/// assert_eq!(hello.checksum(), hash(b"hello world"));
+	/// ```
 	pub fn combine (&mut self, other: &Self) {
 		self.amount += other.amount;
 		self.state = crc32_algorithm::combine(self.checksum(), other.checksum(), other.amount);
@@ -63,6 +144,36 @@ impl Hasher {
 
 }
 
+impl Default for Hasher {
+	fn default () -> Self {
+		Self::new()
+	}
+}
+
+impl core::hash::Hasher for Hasher {
+	fn write (&mut self, bytes: &[u8]) {
+		self.append(bytes);
+	}
+
+	// the checksum is only 32 bits, spread it over all 64 so hash tables
+	// that use the top bits get something out of them too
+	fn finish (&self) -> u64 {
+		(self.checksum() as u64).wrapping_mul(0x9e37_79b9_7f4a_7c15)
+	}
+}
+
+#[cfg(feature = "std")]
+impl std::io::Write for Hasher {
+	fn write (&mut self, buf: &[u8]) -> std::io::Result<usize> {
+		self.append(buf);
+		Ok(buf.len())
+	}
+
+	fn flush (&mut self) -> std::io::Result<()> {
+		Ok(())
+	}
+}
+
 
 
 // actual algorithm
@@ -98,7 +209,7 @@ mod crc32_algorithm {
 					^ CRC32_TABLE[0xc][buf[0x3] as usize ^ ((crc >> 0x18) & 0xFF) as usize]
 					^ CRC32_TABLE[0xd][buf[0x2] as usize ^ ((crc >> 0x10) & 0xFF) as usize]
 					^ CRC32_TABLE[0xe][buf[0x1] as usize ^ ((crc >> 0x08) & 0xFF) as usize]
-					^ CRC32_TABLE[0xf][buf[0x0] as usize ^ ((crc >> 0x00) & 0xFF) as usize]
+					^ CRC32_TABLE[0xf][buf[0x0] as usize ^ (crc & 0xFF) as usize]
 				;
 				buf = &buf[16..];
 			}
@@ -119,10 +230,8 @@ mod crc32_algorithm {
 
 
 
-	#[allow(dead_code)]
 	const GF2_DIM: usize = 32;
 
-	#[allow(dead_code)]
 	fn gf2_matrix_times (mat: &[u32; GF2_DIM], mut vec: u32) -> u32 {
 		let mut sum = 0;
 		let mut idx = 0;
@@ -136,29 +245,27 @@ mod crc32_algorithm {
 		sum
 	}
 
-	#[allow(dead_code)]
 	fn gf2_matrix_square (square: &mut [u32; GF2_DIM], mat: &[u32; GF2_DIM]) {
 		for n in 0..GF2_DIM {
 			square[n] = gf2_matrix_times(mat, mat[n]);
 		}
 	}
 
-	#[allow(dead_code)]
 	pub fn combine (mut crc1: u32, crc2: u32, mut len2: u64) -> u32 {
 		let mut row: u32;
 		let mut even = [0u32; GF2_DIM]; // even power-of-two operators
 		let mut odd  = [0u32; GF2_DIM]; // odd  power-of-two operators
 
 		// degenerate case (also disallow negative lengths)
-		if len2 <= 0 {
+		if len2 == 0 {
 			return crc1;
 		}
 
 		// put operator for one zero bit in odd
 		odd[0] = 0xedb88320; // CRC-32 polynomial, find out more: https://www.youtube.com/watch?v=IHjNdZQreds
 		row = 1;
-		for n in 1..GF2_DIM {
-			odd[n] = row;
+		for operator in odd.iter_mut().skip(1) {
+			*operator = row;
 			row <<= 1;
 		}
 
@@ -210,7 +317,11 @@ mod crc32_algorithm {
 #[cfg(test)]
 mod tests {
 
-	use super::crc32_algorithm;
+	use super::{ crc32_algorithm, combine, hash, BuildHasher, Hasher };
+	use core::hash::{ BuildHasher as _, Hasher as _ };
+
+	#[cfg(not(feature = "std"))]
+	use alloc::vec::Vec;
 
 	#[test]
 	fn crc32_algorithm_slow() {
@@ -228,6 +339,50 @@ mod tests {
 		assert_eq!(crc32_algorithm::update_slow(0, b"\x00\x01\x02\x03\x04\x05\x06\x07\x08\x09\x0A\x0B\x0C\x0D\x0E\x0F\x10\x11\x12\x13\x14\x15\x16\x17\x18\x19\x1A\x1B\x1C\x1D\x1E\x1F"), 0x91267E8A);
 	}
 
+	#[test]
+	fn test_hasher () {
+		let data = (0..1000u32).map(|i| (i * 7 % 251) as u8).collect::<Vec<_>>();
+		assert_eq!(hash(&data), crc32_algorithm::update_slow(0, &data));
+
+		let mut hasher = Hasher::default();
+		for chunk in data.chunks(37) {
+			hasher.append(chunk);
+		}
+		assert_eq!(hasher.checksum(), hash(&data));
+		assert_eq!(hasher.amount(), 1000);
+
+		hasher.reset();
+		assert_eq!(hasher, BuildHasher::default().build_hasher());
+
+		// as a core hasher
+		hasher.write(&data);
+		assert_eq!(hasher.finish(), (hash(&data) as u64).wrapping_mul(0x9e37_79b9_7f4a_7c15));
+		assert_ne!(hasher.finish() >> 32, 0);
+
+		// seeded from the first half, and combined with a seeded second half
+		let (a, b) = data.split_at(400);
+		let mut first = Hasher::new();
+		first.seed(hash(a), a.len() as u64);
+		let mut second = Hasher::new();
+		second.seed(hash(b), b.len() as u64);
+		first.combine(&second);
+		assert_eq!(first.checksum(), hash(&data));
+		assert_eq!(first.amount(), 1000);
+
+		for split in [0, 1, 63, 500, 1000] {
+			let (a, b) = data.split_at(split);
+			assert_eq!(combine(hash(a), hash(b), b.len() as u64), hash(&data));
+		}
+	}
+
+	#[cfg(feature = "std")]
+	#[test]
+	fn test_write () {
+		let mut hasher = Hasher::new();
+		std::io::copy(&mut &b"hello world"[..], &mut hasher).unwrap();
+		assert_eq!(hasher.checksum(), 0x0d4a1185);
+	}
+
 }
 
 
@@ -0,0 +1,297 @@
+// parsing of css color level 4 named colors and rgb()/hsl()/hwb() functions
+// https://www.w3.org/TR/css-color-4/
+
+use crate::color::{ Color, ParseColorError, ParseColorErrorKind };
+
+
+
+static NAMED_COLORS: [(&str, [u8; 3]); 148] = include!("named_colors.rs");
+
+
+
+// `name` is a css named color (case insensitive) or `transparent`,
+// `offset` is the position of `name` in the originally parsed string
+pub(crate) fn parse_named (name: &str, offset: usize) -> Result<Color, ParseColorError> {
+	if name.eq_ignore_ascii_case("transparent") {
+		return Ok(Color::new(0, 0, 0, 0));
+	}
+
+	NAMED_COLORS
+		.binary_search_by(|(known, _)| known.bytes().cmp(name.bytes().map(|b| b.to_ascii_lowercase())))
+		.map(|i| {
+			let [r, g, b] = NAMED_COLORS[i].1;
+			Color::rgb(r, g, b)
+		})
+		.map_err(|_| ParseColorError::new(ParseColorErrorKind::UnknownName, offset))
+}
+
+
+
+// `function` is a whole `name(arguments)` string
+pub(crate) fn parse_function (function: &str, offset: usize) -> Result<Color, ParseColorError> {
+	let open = function.find('(').unwrap_or(function.len());
+	let name = &function[..open];
+
+	let arguments = function[open..].strip_prefix('(')
+		.and_then(|s| s.strip_suffix(')'))
+		.ok_or(ParseColorError::new(ParseColorErrorKind::InvalidSyntax, offset + function.len()))?;
+	let arguments = Arguments::split(arguments, offset + open + 1)?;
+
+	let values = arguments.values;
+	let alpha = arguments.alpha.map_or(Ok(1.0), alpha_value)?;
+
+	let is = |function_name: &str| name.eq_ignore_ascii_case(function_name);
+	let [r, g, b] = if is("rgb") || is("rgba") {
+		[channel_value(values[0])?, channel_value(values[1])?, channel_value(values[2])?]
+	} else if is("hsl") || is("hsla") {
+		hsl_to_rgb(hue_value(values[0])?, percentage_value(values[1])?, percentage_value(values[2])?)
+	} else if is("hwb") && !arguments.legacy {
+		hwb_to_rgb(hue_value(values[0])?, percentage_value(values[1])?, percentage_value(values[2])?)
+	} else {
+		return Err(ParseColorError::new(ParseColorErrorKind::UnknownFunction, offset));
+	};
+
+	Ok(Color::new(to_u8(r), to_u8(g), to_u8(b), to_u8(alpha * 255.0)))
+}
+
+
+
+// a single argument and it's position in the originally parsed string
+#[derive(Clone, Copy)]
+struct Argument<'a> {
+	value: &'a str,
+	position: usize
+}
+
+struct Arguments<'a> {
+	values: [Argument<'a>; 3],
+	alpha: Option<Argument<'a>>,
+	// comma separated i.e. `rgb(1, 2, 3)` instead of `rgb(1 2 3)`
+	legacy: bool
+}
+
+impl<'a> Arguments<'a> {
+
+	// split either `a, b, c[, alpha]` or `a b c[ / alpha]`
+	fn split (arguments: &'a str, offset: usize) -> Result<Self, ParseColorError> {
+		let argument = |value: &'a str| Argument {
+			value,
+			position: offset + (value.as_ptr() as usize - arguments.as_ptr() as usize)
+		};
+		let syntax_error = |position: usize| ParseColorError::new(ParseColorErrorKind::InvalidSyntax, position);
+
+		let mut found = [Argument { value: "", position: offset }; 4];
+		let mut count = 0;
+		let mut alpha_index = None;
+
+		let legacy = arguments.contains(',');
+		if legacy {
+			for value in arguments.split(',').map(|s| s.trim()) {
+				let value = argument(value);
+				if value.value.is_empty() || value.value.contains(char::is_whitespace) || count == found.len() {
+					return Err(syntax_error(value.position));
+				}
+				found[count] = value;
+				count += 1;
+			}
+			if count == 4 {
+				alpha_index = Some(3);
+			}
+		} else {
+			let mut parts = arguments.splitn(2, '/');
+			let channels = parts.next().unwrap_or("");
+
+			for value in channels.split_whitespace() {
+				let value = argument(value);
+				if count == 3 {
+					return Err(syntax_error(value.position));
+				}
+				found[count] = value;
+				count += 1;
+			}
+
+			if let Some(alpha) = parts.next() {
+				let mut alpha_values = alpha.split_whitespace();
+				match (alpha_values.next(), alpha_values.next()) {
+					(Some(value), None) if count == 3 => {
+						found[count] = argument(value);
+						alpha_index = Some(count);
+						count += 1;
+					},
+					_ => return Err(syntax_error(argument(alpha).position))
+				}
+			}
+		}
+
+		if count < 3 || (count == 4 && alpha_index.is_none()) {
+			return Err(syntax_error(offset + arguments.len()));
+		}
+
+		Ok(Self {
+			values: [found[0], found[1], found[2]],
+			alpha: alpha_index.map(|i| found[i]),
+			legacy
+		})
+	}
+
+}
+
+
+
+fn number (s: &str) -> Option<f32> {
+	// `f32::from_str` also accepts things like `inf` and `NaN`
+	s.starts_with(|c: char| c.is_ascii_digit() || matches!(c, '.' | '+' | '-'))
+		.then(|| s.parse::<f32>().ok())
+		.flatten()
+}
+
+fn invalid_argument (argument: Argument) -> ParseColorError {
+	ParseColorError::new(ParseColorErrorKind::InvalidArgument, argument.position)
+}
+
+// red, green or blue as a number 0-255 or a percentage
+fn channel_value (argument: Argument) -> Result<f32, ParseColorError> {
+	if argument.value.eq_ignore_ascii_case("none") {
+		return Ok(0.0);
+	}
+	match argument.value.strip_suffix('%') {
+		Some(percentage) => number(percentage).map(|p| p * 255.0 / 100.0),
+		None => number(argument.value)
+	}.ok_or(invalid_argument(argument))
+}
+
+// alpha as a number 0-1 or a percentage
+fn alpha_value (argument: Argument) -> Result<f32, ParseColorError> {
+	if argument.value.eq_ignore_ascii_case("none") {
+		return Ok(0.0);
+	}
+	match argument.value.strip_suffix('%') {
+		Some(percentage) => number(percentage).map(|p| p / 100.0),
+		None => number(argument.value)
+	}.map(|a| a.clamp(0.0, 1.0)).ok_or(invalid_argument(argument))
+}
+
+// saturation, lightness, whiteness or blackness as 0-1, plain numbers
+// are treated as percentages like the modern css syntax does
+fn percentage_value (argument: Argument) -> Result<f32, ParseColorError> {
+	if argument.value.eq_ignore_ascii_case("none") {
+		return Ok(0.0);
+	}
+	number(argument.value.strip_suffix('%').unwrap_or(argument.value))
+		.map(|p| (p / 100.0).clamp(0.0, 1.0))
+		.ok_or(invalid_argument(argument))
+}
+
+// hue in degrees, from a number or an angle in deg, grad, rad or turn
+fn hue_value (argument: Argument) -> Result<f32, ParseColorError> {
+	if argument.value.eq_ignore_ascii_case("none") {
+		return Ok(0.0);
+	}
+
+	let value = argument.value;
+	let unit_start = value.rfind(|c: char| c.is_ascii_digit() || c == '.').map_or(0, |i| i + 1);
+	let (value, unit) = value.split_at(unit_start);
+
+	let is = |unit_name: &str| unit.eq_ignore_ascii_case(unit_name);
+	let degrees_per_unit = if unit.is_empty() || is("deg") {
+		1.0
+	} else if is("grad") {
+		0.9
+	} else if is("rad") {
+		180.0 / core::f32::consts::PI
+	} else if is("turn") {
+		360.0
+	} else {
+		return Err(invalid_argument(argument));
+	};
+
+	number(value).map(|hue| hue * degrees_per_unit).ok_or(invalid_argument(argument))
+}
+
+
+
+// https://www.w3.org/TR/css-color-4/#hsl-to-rgb
+pub(crate) fn hsl_to_rgb (hue: f32, saturation: f32, lightness: f32) -> [f32; 3] {
+	let hue = hue % 360.0;
+	let hue = if hue < 0.0 { hue + 360.0 } else { hue };
+
+	let f = |n: f32| {
+		let k = (n + hue / 30.0) % 12.0;
+		let a = saturation * lightness.min(1.0 - lightness);
+		(lightness - a * (k - 3.0).min(9.0 - k).clamp(-1.0, 1.0)) * 255.0
+	};
+
+	[f(0.0), f(8.0), f(4.0)]
+}
+
+// https://www.w3.org/TR/css-color-4/#hwb-to-rgb
+fn hwb_to_rgb (hue: f32, whiteness: f32, blackness: f32) -> [f32; 3] {
+	if whiteness + blackness >= 1.0 {
+		let gray = whiteness / (whiteness + blackness) * 255.0;
+		return [gray; 3];
+	}
+
+	hsl_to_rgb(hue, 1.0, 0.5).map(|c| c * (1.0 - whiteness - blackness) + whiteness * 255.0)
+}
+
+pub(crate) fn to_u8 (value: f32) -> u8 {
+	(value.clamp(0.0, 255.0) + 0.5) as u8
+}
+
+
+
+#[cfg(test)]
+mod tests {
+
+	use crate::{ Color, ParseColorErrorKind };
+
+
+
+	#[test]
+	fn test_parse_named () {
+		assert_eq!("rebeccapurple".parse(), Ok(Color::rgb(102, 51, 153)));
+		assert_eq!("RebeccaPurple".parse(), Ok(Color::rgb(102, 51, 153)));
+		assert_eq!("aliceblue".parse(), Ok(Color::rgb(240, 248, 255)));
+		assert_eq!("yellowgreen".parse(), Ok(Color::rgb(154, 205, 50)));
+		assert_eq!("transparent".parse(), Ok(Color::new(0, 0, 0, 0)));
+		assert_eq!(" red ".parse(), Ok(Color::rgb(255, 0, 0)));
+		assert_eq!("notacolor".parse::<Color>().map_err(|e| e.kind()), Err(ParseColorErrorKind::UnknownName));
+	}
+
+	#[test]
+	fn test_parse_rgb () {
+		assert_eq!("rgb(235, 35, 35)".parse(), Ok(Color::rgb(235, 35, 35)));
+		assert_eq!("rgba(235,35,35,0.5)".parse(), Ok(Color::new(235, 35, 35, 128)));
+		assert_eq!("rgb(235 35 35 / 50%)".parse(), Ok(Color::new(235, 35, 35, 128)));
+		assert_eq!("RGB(100% 0% 50%/.25)".parse(), Ok(Color::new(255, 0, 128, 64)));
+		assert_eq!("rgb(300 -5 none)".parse(), Ok(Color::rgb(255, 0, 0)));
+	}
+
+	#[test]
+	fn test_parse_hsl_hwb () {
+		assert_eq!("hsl(4deg 82% 53%)".parse(), Ok(Color::rgb(233, 50, 37)));
+		assert_eq!("hsl(0, 100%, 50%)".parse(), Ok(Color::rgb(255, 0, 0)));
+		assert_eq!("hsla(120, 100%, 25%, 0.5)".parse(), Ok(Color::new(0, 128, 0, 128)));
+		assert_eq!("hsl(0.5turn 100% 50%)".parse(), Ok(Color::rgb(0, 255, 255)));
+		assert_eq!("hsl(-120 100% 50%)".parse(), Ok(Color::rgb(0, 0, 255)));
+		assert_eq!("hwb(0 0% 0%)".parse(), Ok(Color::rgb(255, 0, 0)));
+		assert_eq!("hwb(240 20% 20% / 1)".parse(), Ok(Color::rgb(51, 51, 204)));
+		assert_eq!("hwb(0 60% 60%)".parse(), Ok(Color::rgb(128, 128, 128)));
+	}
+
+	#[test]
+	fn test_parse_function_errors () {
+		let error = |s: &str| s.parse::<Color>().map_err(|e| (e.kind(), e.position())).unwrap_err();
+
+		assert_eq!(error("rgb(1, 2, 3"), (ParseColorErrorKind::InvalidSyntax, 11));
+		assert_eq!(error("rgb(1 2)"), (ParseColorErrorKind::InvalidSyntax, 7));
+		assert_eq!(error("rgb(1 2 3 4)"), (ParseColorErrorKind::InvalidSyntax, 10));
+		assert_eq!(error("rgb(1, 2, , 3)"), (ParseColorErrorKind::InvalidSyntax, 9));
+		assert_eq!(error("rgb(1 2 x)"), (ParseColorErrorKind::InvalidArgument, 8));
+		assert_eq!(error("rgb(1 2 3 / inf)"), (ParseColorErrorKind::InvalidArgument, 12));
+		assert_eq!(error("hsl(4furlongs 82% 53%)"), (ParseColorErrorKind::InvalidArgument, 4));
+		assert_eq!(error("hwb(0, 0%, 0%)"), (ParseColorErrorKind::UnknownFunction, 0));
+		assert_eq!(error("lab(50% 40 59)"), (ParseColorErrorKind::UnknownFunction, 0));
+	}
+
+}
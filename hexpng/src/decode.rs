@@ -0,0 +1,422 @@
+// reading pngs back, checking every chunk's crc, then unfiltering,
+// deinterlacing and expanding the image data to 8 bit rgba
+
+use crate::{ ColorType, Error, MAX_DIMENSION };
+use crate::{ deflate, filter, interlace, png };
+
+#[cfg(not(feature = "std"))]
+use alloc::{ vec, vec::Vec };
+
+
+
+/// a chunk of a png, from [`chunks`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Chunk<'a> {
+	/// the 4 letter type, like `IHDR`
+	pub chunk_type: [u8; 4],
+	/// the data between the length and the crc
+	pub data: &'a [u8]
+}
+
+impl Chunk<'_> {
+
+	/// whether decoders have to understand the chunk to show the png,
+	/// which is when its type starts with an uppercase letter
+	pub fn is_critical (&self) -> bool {
+		self.chunk_type[0].is_ascii_uppercase()
+	}
+
+}
+
+/// the chunks of a png in order, from [`chunks`]
+///
+/// iteration stops after the first chunk that's cut short or whose crc
+/// doesn't match, which is returned as an [`Error::InvalidPng`]
+#[derive(Debug, Clone)]
+pub struct Chunks<'a> {
+	rest: &'a [u8],
+	failed: bool
+}
+
+impl<'a> Iterator for Chunks<'a> {
+	type Item = Result<Chunk<'a>, Error>;
+
+	fn next (&mut self) -> Option<Self::Item> {
+		if self.failed || self.rest.is_empty() {
+			return None;
+		}
+		let chunk = self.read();
+		self.failed = chunk.is_err();
+		Some(chunk)
+	}
+}
+
+impl<'a> Chunks<'a> {
+
+	fn read (&mut self) -> Result<Chunk<'a>, Error> {
+		let cut_short = Error::InvalidPng("a chunk is cut short");
+
+		let (length, rest) = self.rest.split_first_chunk::<4>().ok_or(cut_short)?;
+		let length = u32::from_be_bytes(*length);
+		if length > png::MAX_CHUNK_LEN {
+			return Err(Error::InvalidPng("a chunk is longer than 2^31-1 bytes"));
+		}
+
+		let (chunk_type, rest) = rest.split_first_chunk::<4>().ok_or(cut_short)?;
+		let (data, rest) = rest.split_at_checked(length as usize).ok_or(cut_short)?;
+		let (crc, rest) = rest.split_first_chunk::<4>().ok_or(cut_short)?;
+		if u32::from_be_bytes(*crc) != png::chunk_checksum(chunk_type, data) {
+			return Err(Error::InvalidPng("a chunk's crc doesn't match its data"));
+		}
+
+		self.rest = rest;
+		Ok(Chunk { chunk_type: *chunk_type, data })
+	}
+
+}
+
+/// check the png signature and iterate over the chunks after it
+///
+/// ```
+/// use hexpng::{ PngOptions, chunks };
+///
+/// let png = PngOptions::new().generate(235, 35, 35, 255).unwrap();
+/// let types = chunks(&png).unwrap().map(|chunk| chunk.map(|chunk| chunk.chunk_type)).collect::<Result<Vec<_>, _>>().unwrap();
+/// assert_eq!(types.first(), Some(b"IHDR"));
+/// assert_eq!(types.last(), Some(b"IEND"));
+/// ```
+pub fn chunks (png: &[u8]) -> Result<Chunks<'_>, Error> {
+	let rest = png.strip_prefix(png::HEADER).ok_or(Error::InvalidPng("the png signature is missing"))?;
+	Ok(Chunks { rest, failed: false })
+}
+
+
+
+/// what a png's IHDR chunk says about it
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Header {
+	/// width in pixels
+	pub width: u32,
+	/// height in pixels
+	pub height: u32,
+	/// how the pixels are stored
+	pub color_type: ColorType,
+	/// bits per sample, or per palette index
+	pub bit_depth: u8,
+	/// whether the png is adam7 interlaced
+	pub interlace: bool
+}
+
+impl Header {
+
+	/// parse the data of an IHDR chunk
+	pub fn parse (data: &[u8]) -> Result<Self, Error> {
+		let data: &[u8; 13] = data.try_into().map_err(|_| Error::InvalidPng("the IHDR chunk isn't 13 bytes"))?;
+
+		let width = u32::from_be_bytes([data[0], data[1], data[2], data[3]]);
+		let height = u32::from_be_bytes([data[4], data[5], data[6], data[7]]);
+		if width == 0 || height == 0 || width > MAX_DIMENSION || height > MAX_DIMENSION {
+			return Err(Error::InvalidDimensions { width, height });
+		}
+
+		let color_type = match data[9] {
+			0 => ColorType::Grayscale,
+			2 => ColorType::Rgb,
+			3 => ColorType::Indexed,
+			4 => ColorType::GrayscaleAlpha,
+			6 => ColorType::Rgba,
+			_ => return Err(Error::InvalidPng("unknown color type"))
+		};
+		let bit_depth = data[8];
+		if !color_type.allows_bit_depth(bit_depth) {
+			return Err(Error::InvalidPng("bit depth isn't allowed for the color type"));
+		}
+		if data[10] != 0 || data[11] != 0 {
+			return Err(Error::InvalidPng("unknown compression or filter method"));
+		}
+		let interlace = match data[12] {
+			0 => false,
+			1 => true,
+			_ => return Err(Error::InvalidPng("unknown interlace method"))
+		};
+
+		Ok(Self { width, height, color_type, bit_depth, interlace })
+	}
+
+}
+
+
+
+/// a png decoded with [`decode`]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct DecodedImage {
+	/// the png's IHDR chunk
+	pub header: Header,
+	/// 8 bit rgba pixels, one row right after another
+	pub pixels: Vec<u8>
+}
+
+/// decode a png to 8 bit rgba pixels
+///
+/// 16 bit samples are rounded to 8 bits, smaller ones scaled up, and
+/// palettes and tRNS transparency applied. ancillary chunks are skipped,
+/// so an animated png decodes to the image viewers without apng support show
+///
+/// ```
+/// use hexpng::{ PngOptions, decode };
+///
+/// let png = PngOptions::new().dimensions(2, 1).generate(235, 35, 35, 127).unwrap();
+/// let image = decode(&png).unwrap();
+/// assert_eq!((image.header.width, image.header.height), (2, 1));
+/// assert_eq!(image.pixels, [235, 35, 35, 127, 235, 35, 35, 127]);
+/// ```
+pub fn decode (png: &[u8]) -> Result<DecodedImage, Error> {
+	let mut chunks = chunks(png)?;
+	let header = match chunks.next().transpose()? {
+		Some(chunk) if &chunk.chunk_type == b"IHDR" => Header::parse(chunk.data)?,
+		_ => return Err(Error::InvalidPng("the first chunk isn't IHDR"))
+	};
+
+	let (mut palette, mut transparency, mut idat) = (None, None, Vec::new());
+	let mut ended = false;
+	for chunk in chunks {
+		let chunk = chunk?;
+		match &chunk.chunk_type {
+			b"IEND" => {
+				ended = true;
+				break;
+			},
+			b"IDAT" => idat.extend_from_slice(chunk.data),
+			b"PLTE" => palette = Some(chunk.data),
+			b"tRNS" => transparency = Some(chunk.data),
+			b"IHDR" => return Err(Error::InvalidPng("there's more than one IHDR chunk")),
+			_ if chunk.is_critical() => return Err(Error::InvalidPng("unknown critical chunk")),
+			_ => {}
+		}
+	}
+	if !ended {
+		return Err(Error::InvalidPng("the png ends before its IEND chunk"));
+	}
+
+	let data = image_data(&header, &idat, usize::MAX)?;
+	let pixels = expand(&header, &data, palette, transparency)?;
+	Ok(DecodedImage { header, pixels })
+}
+
+// the unfiltered, deinterlaced scanlines in the zlib stream `idat`,
+// failing with `Error::ImageTooLarge` if they're longer than `limit`
+pub(crate) fn image_data (header: &Header, idat: &[u8], limit: usize) -> Result<Vec<u8>, Error> {
+	let bits_per_pixel = header.color_type.bits_per_pixel(header.bit_depth);
+	let row_len = (header.width as usize).checked_mul(bits_per_pixel).ok_or(Error::ImageTooLarge)?.div_ceil(8);
+
+	// every pass of an interlaced image is filtered like a separate image
+	let images = match header.interlace {
+		true => interlace::passes(header.width, header.height, bits_per_pixel).collect::<Vec<_>>(),
+		false => vec![(row_len, header.height as usize)]
+	};
+	let len = images.iter()
+		.try_fold(0usize, |len, &(row_len, rows)| row_len.checked_add(1)?.checked_mul(rows)?.checked_add(len))
+		.filter(|&len| len <= limit)
+		.ok_or(Error::ImageTooLarge)?;
+
+	let mut data = deflate::unzlib_limited(idat, len)?;
+	if data.len() != len {
+		return Err(Error::InvalidPng("the image data is shorter than the image"));
+	}
+
+	let mut rest = &mut data[..];
+	for &(row_len, rows) in &images {
+		let (image, next) = rest.split_at_mut((row_len + 1) * rows);
+		filter::unfilter(image, row_len, bits_per_pixel.div_ceil(8))?;
+		rest = next;
+	}
+
+	Ok(match header.interlace {
+		true => interlace::deinterlace(&data, header.width, header.height, bits_per_pixel),
+		false => data
+	})
+}
+
+// the unfiltered scanlines `data` as 8 bit rgba
+pub(crate) fn expand (header: &Header, data: &[u8], palette: Option<&[u8]>, transparency: Option<&[u8]>) -> Result<Vec<u8>, Error> {
+	let len = (header.width as usize).checked_mul(header.height as usize)
+		.and_then(|pixels| pixels.checked_mul(4))
+		.ok_or(Error::ImageTooLarge)?;
+	let mut pixels = Vec::new();
+	pixels.try_reserve_exact(len).map_err(|_| Error::ImageTooLarge)?;
+
+	let palette = match (header.color_type, palette) {
+		(ColorType::Indexed, Some(palette)) if !palette.is_empty() && palette.len() % 3 == 0 && palette.len() <= 256 * 3 => palette,
+		(ColorType::Indexed, _) => return Err(Error::InvalidPng("an indexed png needs a PLTE chunk of 1 to 256 entries")),
+		// a suggested palette for viewers that can't show truecolor
+		_ => &[]
+	};
+	// the samples of the one transparent gray level or rgb color
+	let transparent = match header.color_type {
+		ColorType::Grayscale | ColorType::Rgb => transparency.map(|data| data.chunks_exact(2).map(|s| u16::from_be_bytes([s[0], s[1]])).collect::<Vec<_>>()),
+		_ => None
+	};
+
+	let (channels, depth) = (header.color_type.channels(), header.bit_depth);
+	let to_u8 = |sample: u16| match depth {
+		16 => ((sample as u32 * 255 + 32767) / 65535) as u8,
+		depth => (sample as u32 * 255 / ((1 << depth) - 1)) as u8
+	};
+
+	let stride = data.len() / header.height as usize;
+	for row in data.chunks_exact(stride) {
+		let mut samples = samples(&row[1..], depth);
+		for _ in 0..header.width {
+			let mut pixel = [0u16; 4];
+			for sample in &mut pixel[..channels] {
+				*sample = samples.next().unwrap_or(0);
+			}
+			let opaque = if transparent.as_deref() == Some(&pixel[..channels]) { 0 } else { 255 };
+
+			pixels.extend(match header.color_type {
+				ColorType::Grayscale => [to_u8(pixel[0]), to_u8(pixel[0]), to_u8(pixel[0]), opaque],
+				ColorType::GrayscaleAlpha => [to_u8(pixel[0]), to_u8(pixel[0]), to_u8(pixel[0]), to_u8(pixel[1])],
+				ColorType::Rgb => [to_u8(pixel[0]), to_u8(pixel[1]), to_u8(pixel[2]), opaque],
+				ColorType::Rgba => pixel.map(to_u8),
+				ColorType::Indexed => {
+					let index = pixel[0] as usize;
+					let rgb = palette.get(index * 3..index * 3 + 3).ok_or(Error::InvalidPng("a pixel refers to a palette entry that doesn't exist"))?;
+					[rgb[0], rgb[1], rgb[2], transparency.and_then(|alpha| alpha.get(index)).copied().unwrap_or(255)]
+				}
+			});
+		}
+	}
+
+	Ok(pixels)
+}
+
+// the samples of a row one after another, most significant bits first
+// for bit depths below 8
+fn samples (row: &[u8], bit_depth: u8) -> impl Iterator<Item = u16> + '_ {
+	let depth = bit_depth as usize;
+	(0..row.len() * 8 / depth).map(move |i| match depth {
+		16 => u16::from_be_bytes([row[2 * i], row[2 * i + 1]]),
+		_ => (row[i * depth / 8] >> (8 - depth - i * depth % 8)) as u16 & ((1 << depth) - 1)
+	})
+}
+
+
+
+#[cfg(test)]
+mod tests {
+
+	use super::{ decode, chunks, Header };
+	use crate::{ PngOptions, Color, ColorType, Error, Filter, RawImage };
+
+	#[cfg(not(feature = "std"))]
+	use alloc::vec::Vec;
+
+
+
+	// a `width` x `height` image with `colors` distinct pixels that every
+	// color type and bit depth below can store
+	fn pixels (width: usize, height: usize, colors: usize, gray: bool) -> Vec<u8> {
+		(0..width * height).flat_map(|i| {
+			let level = (i * 7 + i / width) % colors * (255 / (colors - 1).max(1));
+			match gray {
+				true => [level as u8, level as u8, level as u8, 255],
+				false => [level as u8, (255 - level) as u8, (level / 2) as u8, 255]
+			}
+		}).collect()
+	}
+
+	#[test]
+	fn test_round_trip () {
+		let (width, height) = (19, 7);
+		let formats = [
+			(ColorType::Grayscale, 1, pixels(width, height, 2, true)),
+			(ColorType::Grayscale, 2, pixels(width, height, 4, true)),
+			(ColorType::Grayscale, 4, pixels(width, height, 16, true)),
+			(ColorType::Grayscale, 8, pixels(width, height, 200, true)),
+			(ColorType::Grayscale, 16, pixels(width, height, 200, true)),
+			(ColorType::GrayscaleAlpha, 8, pixels(width, height, 100, true)),
+			(ColorType::GrayscaleAlpha, 16, pixels(width, height, 100, true)),
+			(ColorType::Rgb, 8, pixels(width, height, 50, false)),
+			(ColorType::Rgb, 16, pixels(width, height, 50, false)),
+			(ColorType::Rgba, 8, pixels(width, height, 50, false)),
+			(ColorType::Rgba, 16, pixels(width, height, 50, false)),
+			(ColorType::Indexed, 1, pixels(width, height, 2, false)),
+			(ColorType::Indexed, 2, pixels(width, height, 4, false)),
+			(ColorType::Indexed, 4, pixels(width, height, 16, false)),
+			(ColorType::Indexed, 8, pixels(width, height, 50, false))
+		];
+
+		for (color_type, bit_depth, pixels) in formats {
+			for filter in [Filter::None, Filter::Sub, Filter::Up, Filter::Average, Filter::Paeth, Filter::Adaptive] {
+				for interlace in [false, true] {
+					let options = PngOptions::new().dimensions(width as u32, height as u32).format(color_type, bit_depth).filter(filter).interlace(interlace);
+					let image = decode(&options.encode_rgba8(&pixels).unwrap()).unwrap();
+					assert_eq!((image.header.color_type, image.header.bit_depth, image.header.interlace), (color_type, bit_depth, interlace));
+					assert_eq!(image.pixels, pixels, "{color_type:?} {bit_depth} {filter:?} interlaced {interlace} didn't round trip");
+				}
+			}
+		}
+	}
+
+	#[test]
+	fn test_transparency () {
+		// a single fully transparent color as tRNS, for grayscale, rgb and indexed
+		let pixels = [[0, 0, 0, 0], [255, 255, 255, 255], [0, 0, 0, 0], [128, 128, 128, 255]].concat();
+		for (color_type, bit_depth) in [(ColorType::Grayscale, 8), (ColorType::Rgb, 16), (ColorType::Indexed, 2)] {
+			let png = PngOptions::new().dimensions(2, 2).format(color_type, bit_depth).encode_rgba8(&pixels).unwrap();
+			assert_eq!(decode(&png).unwrap().pixels, pixels, "{color_type:?} {bit_depth}");
+		}
+
+		let palette = [Color::new(235, 35, 35, 127), Color::rgb(35, 35, 235)];
+		let png = PngOptions::new().dimensions(3, 1).encode(&RawImage::new(&[0b0001_0000], ColorType::Indexed, 2).palette(&palette)).unwrap();
+		assert_eq!(decode(&png).unwrap().pixels, [235, 35, 35, 127, 35, 35, 235, 255, 235, 35, 35, 127]);
+	}
+
+	#[test]
+	fn test_animation () {
+		let colors = [Color::rgb(235, 35, 35), Color::rgb(35, 35, 235)];
+		let png = PngOptions::new().dimensions(4, 4).transition(&colors, 5, (1, 10)).unwrap();
+		assert_eq!(decode(&png).unwrap().pixels, [235, 35, 35, 255].repeat(16));
+	}
+
+	#[test]
+	fn test_chunks () {
+		let png = PngOptions::new().idat_size(10).generate(235, 35, 35, 255).unwrap();
+		let chunks = chunks(&png).unwrap().collect::<Result<Vec<_>, _>>().unwrap();
+		assert_eq!(chunks.first().map(|chunk| chunk.chunk_type), Some(*b"IHDR"));
+		assert_eq!(chunks.last().map(|chunk| chunk.chunk_type), Some(*b"IEND"));
+		assert!(chunks.iter().all(|chunk| chunk.is_critical()));
+
+		assert!(matches!(super::chunks(&png[1..]), Err(Error::InvalidPng(_))));
+
+		// a flipped bit of image data fails its crc, and nothing after it is read
+		let mut corrupt = png.clone();
+		corrupt[8 + 25 + 8] ^= 1;
+		let read = super::chunks(&corrupt).unwrap().collect::<Vec<_>>();
+		assert_eq!(read.len(), 2);
+		assert!(matches!(read[1], Err(Error::InvalidPng(_))));
+		assert!(matches!(decode(&corrupt), Err(Error::InvalidPng(_))));
+
+		// cut short, and without IEND
+		assert!(matches!(decode(&png[..png.len() - 1]), Err(Error::InvalidPng(_))));
+		assert!(matches!(decode(&png[..png.len() - 12]), Err(Error::InvalidPng(_))));
+	}
+
+	#[test]
+	fn test_header () {
+		let png = PngOptions::new().dimensions(300, 20).format(ColorType::Rgba, 16).interlace(true).generate(0, 0, 0, 0).unwrap();
+		let header = Header::parse(&png[16..29]).unwrap();
+		assert_eq!(header, Header { width: 300, height: 20, color_type: ColorType::Rgba, bit_depth: 16, interlace: true });
+
+		let ihdr = |width: u32, bit_depth: u8, color_type: u8, interlace: u8| {
+			[&width.to_be_bytes()[..], &[0, 0, 0, 1, bit_depth, color_type, 0, 0, interlace]].concat()
+		};
+		assert!(Header::parse(&ihdr(1, 8, 6, 0)).is_ok());
+		assert!(matches!(Header::parse(&ihdr(0, 8, 6, 0)), Err(Error::InvalidDimensions { .. })));
+		assert!(matches!(Header::parse(&ihdr(1, 4, 6, 0)), Err(Error::InvalidPng(_))));
+		assert!(matches!(Header::parse(&ihdr(1, 8, 5, 0)), Err(Error::InvalidPng(_))));
+		assert!(matches!(Header::parse(&ihdr(1, 8, 6, 2)), Err(Error::InvalidPng(_))));
+		assert!(matches!(Header::parse(&ihdr(1, 8, 6, 0)[1..]), Err(Error::InvalidPng(_))));
+	}
+
+}
@@ -0,0 +1,1119 @@
+// a zlib (rfc 1950) wrapped deflate (rfc 1951) encoder, lz77 matching
+// with hash chains, then each block stored or huffman coded with the
+// fixed or its own dynamic codes, whichever is smallest
+
+use crate::Error;
+
+#[cfg(not(feature = "std"))]
+use alloc::{ vec, vec::Vec };
+
+
+
+const WINDOW: usize = 1 << 15;
+const MIN_MATCH: usize = 3;
+const MAX_MATCH: usize = 258;
+const HASH_BITS: u32 = 15;
+// matches this short are dropped by `Strategy::Filtered`
+const FILTERED_MAX_DISCARD: usize = 5;
+// tokens per block, every block gets huffman codes fitted to its own data
+const BLOCK_TOKENS: usize = 1 << 14;
+// input a streaming encoder collects before compressing it
+const SEGMENT: usize = 1 << 17;
+const STORED_LEN: usize = u16::MAX as usize;
+
+const END_OF_BLOCK: usize = 256;
+const LITERAL_LENGTH_CODES: usize = 286;
+const DISTANCE_CODES: usize = 30;
+const CODE_LENGTH_CODES: usize = 19;
+
+const LENGTH_BASE: [u16; 29] = [3, 4, 5, 6, 7, 8, 9, 10, 11, 13, 15, 17, 19, 23, 27, 31, 35, 43, 51, 59, 67, 83, 99, 115, 131, 163, 195, 227, 258];
+const LENGTH_EXTRA: [u8; 29] = [0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 3, 3, 3, 3, 4, 4, 4, 4, 5, 5, 5, 5, 0];
+const DISTANCE_BASE: [u16; 30] = [1, 2, 3, 4, 5, 7, 9, 13, 17, 25, 33, 49, 65, 97, 129, 193, 257, 385, 513, 769, 1025, 1537, 2049, 3073, 4097, 6145, 8193, 12289, 16385, 24577];
+const DISTANCE_EXTRA: [u8; 30] = [0, 0, 0, 0, 1, 1, 2, 2, 3, 3, 4, 4, 5, 5, 6, 6, 7, 7, 8, 8, 9, 9, 10, 10, 11, 11, 12, 12, 13, 13];
+// the order code length code lengths are sent in, rarely used ones last
+const CODE_LENGTH_ORDER: [usize; 19] = [16, 17, 18, 0, 8, 7, 9, 6, 10, 5, 11, 4, 12, 3, 13, 2, 14, 1, 15];
+
+
+
+/// how hard the deflate encoder tries to make the png smaller, for
+/// [`PngOptions::compression`](crate::PngOptions::compression)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Compression {
+	/// like zlib's levels, 0 stores the data without compressing it, 1
+	/// is the fastest and 9 the smallest, anything above 9 is 9
+	Level(u8),
+	/// slower than level 9, following every earlier match in the window
+	Max
+}
+
+/// level 6, the same default as zlib
+impl Default for Compression {
+	fn default () -> Self {
+		Self::Level(6)
+	}
+}
+
+impl Compression {
+
+	// how hard lz77 looks for matches, `None` when it doesn't
+	fn effort (self) -> Option<Effort> {
+		let (chain, lazy, nice) = match self {
+			Self::Level(0) => return None,
+			Self::Level(1) => (4, 0, 16),
+			Self::Level(2) => (8, 0, 32),
+			Self::Level(3) => (32, 0, 64),
+			Self::Level(4) => (16, 8, 64),
+			Self::Level(5) => (32, 16, 128),
+			Self::Level(6) => (128, 32, MAX_MATCH),
+			Self::Level(7) => (256, 64, MAX_MATCH),
+			Self::Level(8) => (1024, 128, MAX_MATCH),
+			Self::Level(_) => (4096, MAX_MATCH, MAX_MATCH),
+			Self::Max => (WINDOW, MAX_MATCH, MAX_MATCH)
+		};
+		Some(Effort { chain, lazy, nice })
+	}
+
+	// the FLEVEL field of the zlib header
+	fn zlib_level (self) -> u8 {
+		match self {
+			Self::Level(0..=1) => 0,
+			Self::Level(2..=5) => 1,
+			Self::Level(6) => 2,
+			Self::Level(_) | Self::Max => 3
+		}
+	}
+
+}
+
+/// what kind of matches the deflate encoder looks for, for
+/// [`PngOptions::strategy`](crate::PngOptions::strategy), the same as
+/// zlib's strategies
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum Strategy {
+	/// matches anywhere in the window
+	#[default]
+	Default,
+	/// literals instead of short matches, for filtered image data that's
+	/// mostly small but somewhat random values
+	Filtered,
+	/// only runs of the same byte, fast and good for images with long
+	/// runs of a single gray level
+	Rle,
+	/// no matches, only huffman coding the bytes
+	HuffmanOnly,
+	/// no compression at all, like level 0
+	Stored
+}
+
+impl Strategy {
+
+	/// the strategies [`PngOptions::optimize`](crate::PngOptions::optimize) tries,
+	/// storing is never smaller as blocks are already stored when it is
+	pub(crate) const OPTIMIZE: [Self; 4] = [Self::Default, Self::Filtered, Self::Rle, Self::HuffmanOnly];
+
+}
+
+// how many earlier positions with the same hash are tried per match, the
+// match length below which the next position is checked for a longer
+// match, and the length that's long enough to stop looking
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Effort {
+	chain: usize,
+	lazy: usize,
+	nice: usize
+}
+
+
+
+// zlib stream of `data` with the default compression
+pub(crate) fn zlib (data: &[u8]) -> Vec<u8> {
+	zlib_with(data, Compression::default(), Strategy::Default)
+}
+
+// zlib stream of `data`, deflated with a 32k window
+pub(crate) fn zlib_with (data: &[u8], compression: Compression, strategy: Strategy) -> Vec<u8> {
+	let mut writer = BitWriter { out: zlib_header(compression).to_vec(), buffer: 0, count: 0 };
+	compress(data, 0, compression, strategy, true, &mut writer);
+	writer.align();
+
+	let mut out = writer.out;
+	out.extend(adler32(data).to_be_bytes());
+	out
+}
+
+// deflate with a 32k window and no dictionary, the check bits make the
+// header a multiple of 31
+fn zlib_header (compression: Compression) -> [u8; 2] {
+	let (cmf, flg) = (0x78u16, (compression.zlib_level() as u16) << 6);
+	let flg = flg + 31 - (cmf << 8 | flg) % 31;
+	[cmf as u8, flg as u8]
+}
+
+// deflate `data[start..]` with `data[..start]` as the bytes before it
+fn compress (data: &[u8], start: usize, compression: Compression, strategy: Strategy, last: bool, writer: &mut BitWriter) {
+	match (compression.effort(), strategy) {
+		(Some(effort), strategy) if strategy != Strategy::Stored => deflate(data, start, effort, strategy, last, writer),
+		_ if start == data.len() && !last => {},
+		_ => write_stored_blocks(&data[start..], last, writer)
+	}
+}
+
+fn adler32 (data: &[u8]) -> u32 {
+	let mut adler = Adler32::default();
+	adler.update(data);
+	adler.checksum()
+}
+
+// the checksum at the end of a zlib stream, updated as the data arrives
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Adler32 {
+	a: u32,
+	b: u32
+}
+
+impl Default for Adler32 {
+	fn default () -> Self {
+		Self { a: 1, b: 0 }
+	}
+}
+
+impl Adler32 {
+
+	const MOD: u32 = 65521;
+	// the most bytes that can be summed before `b` could overflow
+	const CHUNK: usize = 5552;
+
+	fn update (&mut self, data: &[u8]) {
+		for chunk in data.chunks(Self::CHUNK) {
+			for &byte in chunk {
+				self.a += byte as u32;
+				self.b += self.a;
+			}
+			self.a %= Self::MOD;
+			self.b %= Self::MOD;
+		}
+	}
+
+	fn checksum (&self) -> u32 {
+		self.b << 16 | self.a
+	}
+
+}
+
+
+
+// a zlib stream compressed as the data arrives, instead of all at once
+//
+// input is compressed a segment at a time, with the last 32k before it
+// kept around for matches, so at most a segment and a window of input
+// are held on to whatever the length of the stream
+pub(crate) struct ZlibEncoder {
+	compression: Compression,
+	strategy: Strategy,
+	// the window already compressed, followed by the pending input
+	input: Vec<u8>,
+	// where the pending input starts
+	start: usize,
+	adler: Adler32,
+	writer: BitWriter
+}
+
+impl ZlibEncoder {
+
+	pub(crate) fn new (compression: Compression, strategy: Strategy) -> Self {
+		Self {
+			compression,
+			strategy,
+			input: Vec::new(),
+			start: 0,
+			adler: Adler32::default(),
+			writer: BitWriter { out: zlib_header(compression).to_vec(), buffer: 0, count: 0 }
+		}
+	}
+
+	// add `data` to the stream, compressing it once there's a segment of it
+	pub(crate) fn write (&mut self, data: &[u8]) {
+		self.adler.update(data);
+		self.input.extend_from_slice(data);
+
+		if self.input.len() - self.start >= SEGMENT {
+			compress(&self.input, self.start, self.compression, self.strategy, false, &mut self.writer);
+			let keep = self.input.len() - self.input.len().min(WINDOW);
+			self.input.drain(..keep);
+			self.start = self.input.len();
+		}
+	}
+
+	// the compressed bytes written since the last call
+	pub(crate) fn output (&mut self) -> Vec<u8> {
+		core::mem::take(&mut self.writer.out)
+	}
+
+	// compress the rest of the input and end the stream, returning the
+	// compressed bytes not taken with `output`, nothing can be written
+	// after this
+	pub(crate) fn finish (&mut self) -> Vec<u8> {
+		compress(&self.input, self.start, self.compression, self.strategy, true, &mut self.writer);
+		self.writer.align();
+		self.writer.out.extend(self.adler.checksum().to_be_bytes());
+		self.output()
+	}
+
+}
+
+
+
+// bits are packed starting from the least significant bit of each byte
+struct BitWriter {
+	out: Vec<u8>,
+	buffer: u64,
+	count: u32
+}
+
+impl BitWriter {
+
+	fn write (&mut self, bits: u32, count: u32) {
+		self.buffer |= (bits as u64) << self.count;
+		self.count += count;
+		while self.count >= 8 {
+			self.out.push(self.buffer as u8);
+			self.buffer >>= 8;
+			self.count -= 8;
+		}
+	}
+
+	// pad to a byte boundary with zeros
+	fn align (&mut self) {
+		if self.count > 0 {
+			self.out.push(self.buffer as u8);
+			self.buffer = 0;
+			self.count = 0;
+		}
+	}
+
+}
+
+
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Token {
+	Literal(u8),
+	Match { length: u16, distance: u16 }
+}
+
+impl Token {
+
+	// bytes of input the token stands for
+	fn len (&self) -> usize {
+		match self {
+			Token::Literal(_) => 1,
+			Token::Match { length, .. } => *length as usize
+		}
+	}
+
+}
+
+fn length_symbol (length: u16) -> usize {
+	LENGTH_BASE.partition_point(|&base| base <= length) - 1
+}
+
+fn distance_symbol (distance: u16) -> usize {
+	DISTANCE_BASE.partition_point(|&base| base <= distance) - 1
+}
+
+
+
+// finds earlier occurrences of the bytes at a position, positions are
+// chained by the hash of their first 3 bytes, as usize so inputs past
+// 4 GiB don't wrap around
+struct Matcher<'a> {
+	data: &'a [u8],
+	// most recent position with each hash
+	head: Vec<usize>,
+	// the position before it with the same hash, by position % WINDOW
+	prev: Vec<usize>,
+	inserted: usize
+}
+
+impl<'a> Matcher<'a> {
+
+	const NONE: usize = usize::MAX;
+
+	fn new (data: &'a [u8]) -> Self {
+		Self { data, head: vec![Self::NONE; 1 << HASH_BITS], prev: vec![Self::NONE; WINDOW], inserted: 0 }
+	}
+
+	fn hash (&self, i: usize) -> usize {
+		let bytes = (self.data[i] as u32) << 16 | (self.data[i + 1] as u32) << 8 | self.data[i + 2] as u32;
+		(bytes.wrapping_mul(0x9e3779b1) >> (32 - HASH_BITS)) as usize
+	}
+
+	// add every position before `end` to the chains
+	fn insert_until (&mut self, end: usize) {
+		let end = end.min(self.data.len().saturating_sub(MIN_MATCH - 1));
+		while self.inserted < end {
+			let hash = self.hash(self.inserted);
+			self.prev[self.inserted % WINDOW] = self.head[hash];
+			self.head[hash] = self.inserted;
+			self.inserted += 1;
+		}
+	}
+
+	// the (length, distance) of the longest earlier match for position
+	// `i`, the length is 0 when there isn't one
+	fn longest (&self, i: usize, effort: Effort) -> (usize, usize) {
+		let max = (self.data.len() - i).min(MAX_MATCH);
+		if max < MIN_MATCH {
+			return (0, 0);
+		}
+
+		let (mut length, mut distance) = (0, 0);
+		let mut candidate = self.head[self.hash(i)];
+		for _ in 0..effort.chain {
+			if candidate == Self::NONE || i - candidate > WINDOW {
+				break;
+			}
+			let start = candidate;
+
+			// can't be longer unless the byte just past the current best matches
+			if self.data[start + length] == self.data[i + length] {
+				let matching = self.data[start..start + max].iter().zip(&self.data[i..i + max]).take_while(|(a, b)| a == b).count();
+				if matching > length {
+					(length, distance) = (matching, i - start);
+					if length >= effort.nice.min(max) {
+						break;
+					}
+				}
+			}
+
+			candidate = self.prev[start % WINDOW];
+		}
+
+		if length < MIN_MATCH { (0, 0) } else { (length, distance) }
+	}
+
+}
+
+// the length of the run of the byte before position `i` starting at `i`,
+// 0 when it's too short to be a match
+fn run (data: &[u8], i: usize) -> usize {
+	let length = match i.checked_sub(1) {
+		Some(previous) => data[i..].iter().take(MAX_MATCH).take_while(|&&byte| byte == data[previous]).count(),
+		None => 0
+	};
+	if length < MIN_MATCH { 0 } else { length }
+}
+
+// `data[start..]` as literals and back references, which can reach back
+// before `start`, a match is put off by a byte when the next position
+// has a longer one
+fn lz77 (data: &[u8], start: usize, effort: Effort, strategy: Strategy) -> Vec<Token> {
+	match strategy {
+		Strategy::HuffmanOnly => return data[start..].iter().map(|&byte| Token::Literal(byte)).collect(),
+		Strategy::Rle => {
+			let mut tokens = Vec::new();
+			let mut i = start;
+			while i < data.len() {
+				match run(data, i) {
+					0 => tokens.push(Token::Literal(data[i])),
+					length => tokens.push(Token::Match { length: length as u16, distance: 1 })
+				}
+				i += tokens.last().map_or(1, Token::len);
+			}
+			return tokens;
+		},
+		_ => {}
+	}
+
+	let mut matcher = Matcher::new(data);
+	let mut tokens = Vec::new();
+	let longest = |matcher: &Matcher, i: usize| match matcher.longest(i, effort) {
+		(length, _) if strategy == Strategy::Filtered && length <= FILTERED_MAX_DISCARD => (0, 0),
+		found => found
+	};
+
+	let mut i = start;
+	while i < data.len() {
+		matcher.insert_until(i);
+		let (mut length, mut distance) = longest(&matcher, i);
+
+		if length > 0 && length < effort.lazy && i + 1 < data.len() {
+			matcher.insert_until(i + 1);
+			let (next_length, next_distance) = longest(&matcher, i + 1);
+			if next_length > length {
+				tokens.push(Token::Literal(data[i]));
+				i += 1;
+				(length, distance) = (next_length, next_distance);
+			}
+		}
+
+		if length > 0 {
+			tokens.push(Token::Match { length: length as u16, distance: distance as u16 });
+			i += length;
+		} else {
+			tokens.push(Token::Literal(data[i]));
+			i += 1;
+		}
+	}
+
+	tokens
+}
+
+
+
+// a canonical huffman code, with the codes bit reversed so they can be
+// written least significant bit first
+struct Huffman {
+	lengths: Vec<u8>,
+	codes: Vec<u16>
+}
+
+impl Huffman {
+
+	fn new (lengths: Vec<u8>) -> Self {
+		let mut count = [0u16; 16];
+		for &length in &lengths {
+			count[length as usize] += 1;
+		}
+		count[0] = 0;
+
+		let mut next = [0u16; 16];
+		let mut code = 0;
+		for bits in 1..16 {
+			code = (code + count[bits - 1]) << 1;
+			next[bits] = code;
+		}
+
+		let codes = lengths.iter().map(|&length| match length {
+			0 => 0,
+			length => {
+				let code = next[length as usize];
+				next[length as usize] += 1;
+				code.reverse_bits() >> (16 - length)
+			}
+		}).collect();
+
+		Self { lengths, codes }
+	}
+
+	// the codes of the fixed huffman block type
+	fn fixed () -> (Self, Self) {
+		let literal_length = (0..288).map(|symbol| match symbol {
+			0..=143 => 8,
+			144..=255 => 9,
+			256..=279 => 7,
+			_ => 8
+		}).collect();
+		(Self::new(literal_length), Self::new(vec![5; 32]))
+	}
+
+	fn write (&self, writer: &mut BitWriter, symbol: usize) {
+		writer.write(self.codes[symbol] as u32, self.lengths[symbol] as u32);
+	}
+
+	// bits taken by symbols with these frequencies
+	fn cost (&self, frequencies: &[u32]) -> u64 {
+		frequencies.iter().zip(&self.lengths).map(|(&frequency, &length)| frequency as u64 * length as u64).sum()
+	}
+
+}
+
+// code lengths of an optimal huffman code for `frequencies`, none
+// longer than `limit`
+//
+// when the tree is too deep the frequencies are halved, flattening it,
+// until it fits
+fn code_lengths (frequencies: &[u32], limit: u8) -> Vec<u8> {
+	let mut lengths = vec![0u8; frequencies.len()];
+	let mut leaves = frequencies.iter().enumerate()
+		.filter(|&(_, &frequency)| frequency > 0)
+		.map(|(symbol, &frequency)| (frequency as u64, symbol))
+		.collect::<Vec<_>>();
+
+	// a code needs 2 symbols to be complete
+	match leaves.len() {
+		0 => return lengths,
+		1 => {
+			let other = if leaves[0].1 == 0 { 1 } else { 0 };
+			lengths[leaves[0].1] = 1;
+			lengths[other] = 1;
+			return lengths;
+		},
+		_ => {}
+	}
+	leaves.sort_unstable();
+
+	loop {
+		// the two queue construction, leaves and internal nodes are
+		// both taken in order of weight
+		let n = leaves.len();
+		let mut weights = leaves.iter().map(|&(weight, _)| weight).collect::<Vec<_>>();
+		let mut parents = vec![0usize; 2 * n - 1];
+		let (mut leaf, mut internal) = (0, n);
+
+		for node in n..2 * n - 1 {
+			let mut smallest = || {
+				let pick = if leaf < n && (internal >= node || weights[leaf] <= weights[internal]) { &mut leaf } else { &mut internal };
+				*pick += 1;
+				*pick - 1
+			};
+			let (a, b) = (smallest(), smallest());
+			weights.push(weights[a] + weights[b]);
+			parents[a] = node;
+			parents[b] = node;
+		}
+
+		let mut depths = vec![0u8; 2 * n - 1];
+		for node in (0..2 * n - 2).rev() {
+			depths[node] = depths[parents[node]] + 1;
+		}
+
+		if depths[..n].iter().all(|&depth| depth <= limit) {
+			for (&(_, symbol), &depth) in leaves.iter().zip(&depths) {
+				lengths[symbol] = depth;
+			}
+			return lengths;
+		}
+
+		for (weight, _) in &mut leaves {
+			*weight = weight.div_ceil(2);
+		}
+	}
+}
+
+// code lengths run length encoded as (symbol, extra bits) with the code
+// length alphabet, 16 repeats the previous length 3-6 times, 17 and 18
+// repeat 0 3-10 and 11-138 times
+fn run_lengths (lengths: &[u8]) -> Vec<(u8, u8)> {
+	let mut symbols = Vec::new();
+
+	let mut i = 0;
+	while i < lengths.len() {
+		let length = lengths[i];
+		let mut run = lengths[i..].iter().take_while(|&&l| l == length).count();
+		i += run;
+
+		if length == 0 {
+			while run >= 11 {
+				let repeat = run.min(138);
+				symbols.push((18, (repeat - 11) as u8));
+				run -= repeat;
+			}
+			if run >= 3 {
+				symbols.push((17, (run - 3) as u8));
+				run = 0;
+			}
+		} else {
+			symbols.push((length, 0));
+			run -= 1;
+			while run >= 3 {
+				let repeat = run.min(6);
+				symbols.push((16, (repeat - 3) as u8));
+				run -= repeat;
+			}
+		}
+		symbols.extend((0..run).map(|_| (length, 0)));
+	}
+
+	symbols
+}
+
+fn code_length_extra (symbol: u8) -> u32 {
+	match symbol {
+		16 => 2,
+		17 => 3,
+		18 => 7,
+		_ => 0
+	}
+}
+
+
+
+// deflate `data[start..]` as blocks of up to BLOCK_TOKENS tokens, the
+// last one ending the stream if `last` is set
+fn deflate (data: &[u8], mut start: usize, effort: Effort, strategy: Strategy, last: bool, writer: &mut BitWriter) {
+	let tokens = lz77(data, start, effort, strategy);
+
+	let mut blocks = tokens.chunks(BLOCK_TOKENS).peekable();
+	if blocks.peek().is_none() && last {
+		write_block(&[], &[], true, writer);
+	}
+
+	while let Some(block) = blocks.next() {
+		let len = block.iter().map(Token::len).sum::<usize>();
+		write_block(block, &data[start..start + len], last && blocks.peek().is_none(), writer);
+		start += len;
+	}
+}
+
+// write `tokens`, which stand for `bytes`, as whichever block type is smallest
+fn write_block (tokens: &[Token], bytes: &[u8], last: bool, writer: &mut BitWriter) {
+	let mut literal_length_frequencies = [0u32; LITERAL_LENGTH_CODES];
+	let mut distance_frequencies = [0u32; DISTANCE_CODES];
+	// extra bits of lengths and distances, the same for every block type
+	let mut extra = 0u64;
+
+	literal_length_frequencies[END_OF_BLOCK] = 1;
+	for token in tokens {
+		match *token {
+			Token::Literal(byte) => literal_length_frequencies[byte as usize] += 1,
+			Token::Match { length, distance } => {
+				let (length, distance) = (length_symbol(length), distance_symbol(distance));
+				literal_length_frequencies[257 + length] += 1;
+				distance_frequencies[distance] += 1;
+				extra += LENGTH_EXTRA[length] as u64 + DISTANCE_EXTRA[distance] as u64;
+			}
+		}
+	}
+
+	// dynamic codes, the distance code needs at least one length even
+	// when there are no matches
+	let literal_length = Huffman::new(code_lengths(&literal_length_frequencies, 15));
+	let mut distance_lengths = code_lengths(&distance_frequencies, 15);
+	if distance_lengths.iter().all(|&length| length == 0) {
+		distance_lengths[..2].copy_from_slice(&[1, 1]);
+	}
+	let distance = Huffman::new(distance_lengths);
+
+	let literal_lengths = 257 + literal_length.lengths[257..].iter().rposition(|&length| length > 0).map_or(0, |i| i + 1);
+	let distances = distance.lengths.iter().rposition(|&length| length > 0).map_or(1, |i| i + 1);
+	let runs = run_lengths(&[&literal_length.lengths[..literal_lengths], &distance.lengths[..distances]].concat());
+
+	let mut code_length_frequencies = [0u32; CODE_LENGTH_CODES];
+	for &(symbol, _) in &runs {
+		code_length_frequencies[symbol as usize] += 1;
+	}
+	let code_length = Huffman::new(code_lengths(&code_length_frequencies, 7));
+	let code_lengths = 4.max(CODE_LENGTH_ORDER.iter().rposition(|&symbol| code_length.lengths[symbol] > 0).map_or(0, |i| i + 1));
+
+	let dynamic_cost = 3 + 5 + 5 + 4 + 3 * code_lengths as u64
+		+ runs.iter().map(|&(symbol, _)| code_length.lengths[symbol as usize] as u64 + code_length_extra(symbol) as u64).sum::<u64>()
+		+ literal_length.cost(&literal_length_frequencies) + distance.cost(&distance_frequencies) + extra;
+
+	let (fixed_literal_length, fixed_distance) = Huffman::fixed();
+	let fixed_cost = 3 + fixed_literal_length.cost(&literal_length_frequencies) + fixed_distance.cost(&distance_frequencies) + extra;
+
+	// each stored block is a header padded to a byte, and 2 lengths
+	let stored_blocks = bytes.len().div_ceil(STORED_LEN).max(1) as u64;
+	let stored_cost = stored_blocks * (3 + 7 + 32) + 8 * bytes.len() as u64;
+
+	if stored_cost < dynamic_cost.min(fixed_cost) {
+		write_stored_blocks(bytes, last, writer);
+	} else if fixed_cost <= dynamic_cost {
+		writer.write(last as u32 | 1 << 1, 3);
+		write_tokens(tokens, &fixed_literal_length, &fixed_distance, writer);
+	} else {
+		writer.write(last as u32 | 2 << 1, 3);
+		writer.write((literal_lengths - 257) as u32, 5);
+		writer.write((distances - 1) as u32, 5);
+		writer.write((code_lengths - 4) as u32, 4);
+		for &symbol in &CODE_LENGTH_ORDER[..code_lengths] {
+			writer.write(code_length.lengths[symbol] as u32, 3);
+		}
+		for &(symbol, bits) in &runs {
+			code_length.write(writer, symbol as usize);
+			writer.write(bits as u32, code_length_extra(symbol));
+		}
+		write_tokens(tokens, &literal_length, &distance, writer);
+	}
+}
+
+// `bytes` as as many stored blocks as it takes
+fn write_stored_blocks (bytes: &[u8], last: bool, writer: &mut BitWriter) {
+	let mut chunks = bytes.chunks(STORED_LEN).peekable();
+	if chunks.peek().is_none() {
+		write_stored(&[], last, writer);
+	}
+	while let Some(chunk) = chunks.next() {
+		write_stored(chunk, last && chunks.peek().is_none(), writer);
+	}
+}
+
+fn write_stored (bytes: &[u8], last: bool, writer: &mut BitWriter) {
+	writer.write(last as u32, 3);
+	writer.align();
+	let len = bytes.len() as u16;
+	writer.out.extend(len.to_le_bytes());
+	writer.out.extend((!len).to_le_bytes());
+	writer.out.extend_from_slice(bytes);
+}
+
+fn write_tokens (tokens: &[Token], literal_length: &Huffman, distance: &Huffman, writer: &mut BitWriter) {
+	for token in tokens {
+		match *token {
+			Token::Literal(byte) => literal_length.write(writer, byte as usize),
+			Token::Match { length, distance: offset } => {
+				let symbol = length_symbol(length);
+				literal_length.write(writer, 257 + symbol);
+				writer.write((length - LENGTH_BASE[symbol]) as u32, LENGTH_EXTRA[symbol] as u32);
+
+				let symbol = distance_symbol(offset);
+				distance.write(writer, symbol);
+				writer.write((offset - DISTANCE_BASE[symbol]) as u32, DISTANCE_EXTRA[symbol] as u32);
+			}
+		}
+	}
+	literal_length.write(writer, END_OF_BLOCK);
+}
+
+
+
+// decompress a zlib stream, `None` if it's corrupt or its checksum doesn't
+// match
+#[cfg(test)]
+pub(crate) fn unzlib (data: &[u8]) -> Option<Vec<u8>> {
+	unzlib_limited(data, usize::MAX).ok()
+}
+
+// what decompressing fails with when the stream itself is broken
+const CORRUPT: Error = Error::InvalidPng("the deflate data is corrupt or too long");
+
+// decompress a zlib stream that should decompress to at most `limit`
+// bytes, failing with what's wrong with it, or with
+// `Error::ImageTooLarge` if the output doesn't fit in memory
+pub(crate) fn unzlib_limited (data: &[u8], limit: usize) -> Result<Vec<u8>, Error> {
+	let (&[cmf, flg], rest) = data.split_first_chunk::<2>().ok_or(Error::InvalidPng("the zlib stream is cut short"))?;
+	if cmf & 0x0f != 8 || cmf >> 4 > 7 {
+		return Err(Error::InvalidPng("the zlib stream isn't deflate with a window of at most 32k"));
+	}
+	if flg & 0x20 != 0 {
+		return Err(Error::InvalidPng("the zlib stream needs a preset dictionary"));
+	}
+	if !(cmf as u16 * 256 + flg as u16).is_multiple_of(31) {
+		return Err(Error::InvalidPng("the zlib header's check bits are wrong"));
+	}
+
+	let mut reader = BitReader { data: rest, position: 0 };
+	let out = inflate(&mut reader, limit)?;
+	if out.len() > limit {
+		return Err(CORRUPT);
+	}
+
+	let end = reader.position.div_ceil(8);
+	let checksum = rest.get(end..).and_then(|rest| rest.first_chunk::<4>()).ok_or(Error::InvalidPng("the zlib stream is cut short"))?;
+	match u32::from_be_bytes(*checksum) == adler32(&out) {
+		true => Ok(out),
+		false => Err(Error::InvalidPng("the zlib stream's adler32 checksum doesn't match"))
+	}
+}
+
+struct BitReader<'a> {
+	data: &'a [u8],
+	// in bits
+	position: usize
+}
+
+impl BitReader<'_> {
+
+	fn read (&mut self, count: u32) -> Result<u32, Error> {
+		let mut bits = 0;
+		for i in 0..count {
+			let byte = self.data.get(self.position / 8).ok_or(CORRUPT)?;
+			bits |= ((byte >> (self.position % 8)) as u32 & 1) << i;
+			self.position += 1;
+		}
+		Ok(bits)
+	}
+
+}
+
+// decodes canonical huffman codes one bit at a time, like zlib's puff.c
+struct Decoder {
+	// codes of each length
+	count: [u16; 16],
+	// symbols ordered by code
+	symbols: Vec<u16>
+}
+
+impl Decoder {
+
+	fn new (lengths: &[u8]) -> Self {
+		let mut count = [0u16; 16];
+		for &length in lengths {
+			count[length as usize] += 1;
+		}
+		count[0] = 0;
+
+		let mut symbols = (0..lengths.len() as u16).filter(|&symbol| lengths[symbol as usize] > 0).collect::<Vec<_>>();
+		symbols.sort_by_key(|&symbol| lengths[symbol as usize]);
+		Self { count, symbols }
+	}
+
+	fn decode (&self, reader: &mut BitReader) -> Result<usize, Error> {
+		let (mut code, mut first, mut index) = (0i32, 0i32, 0i32);
+		for length in 1..16 {
+			code |= reader.read(1)? as i32;
+			let count = self.count[length] as i32;
+			if code - count < first {
+				return self.symbols.get((index + code - first) as usize).map(|&symbol| symbol as usize).ok_or(CORRUPT);
+			}
+			index += count;
+			first = (first + count) << 1;
+			code <<= 1;
+		}
+		Err(CORRUPT)
+	}
+
+}
+
+// make room for `additional` more bytes of output, failing instead of
+// aborting when a small stream claims a huge image
+fn reserve (out: &mut Vec<u8>, additional: usize) -> Result<(), Error> {
+	out.try_reserve(additional).map_err(|_| Error::ImageTooLarge)
+}
+
+fn inflate (reader: &mut BitReader, limit: usize) -> Result<Vec<u8>, Error> {
+	let mut out = Vec::new();
+
+	loop {
+		let last = reader.read(1)? == 1;
+		match reader.read(2)? {
+			0 => {
+				reader.position = reader.position.div_ceil(8) * 8;
+				let (len, nlen) = (reader.read(16)? as u16, reader.read(16)? as u16);
+				if len != !nlen {
+					return Err(CORRUPT);
+				}
+				let start = reader.position / 8;
+				if out.len() + len as usize > limit {
+					return Err(CORRUPT);
+				}
+				let bytes = reader.data.get(start..start + len as usize).ok_or(CORRUPT)?;
+				reserve(&mut out, bytes.len())?;
+				out.extend_from_slice(bytes);
+				reader.position += len as usize * 8;
+			},
+			1 => {
+				let (literal_length, distance) = Huffman::fixed();
+				inflate_block(reader, &Decoder::new(&literal_length.lengths), &Decoder::new(&distance.lengths), &mut out, limit)?;
+			},
+			2 => {
+				let literal_lengths = reader.read(5)? as usize + 257;
+				let distances = reader.read(5)? as usize + 1;
+				let code_lengths = reader.read(4)? as usize + 4;
+
+				let mut lengths = [0u8; CODE_LENGTH_CODES];
+				for &symbol in &CODE_LENGTH_ORDER[..code_lengths] {
+					lengths[symbol] = reader.read(3)? as u8;
+				}
+				let code_length = Decoder::new(&lengths);
+
+				let mut lengths = Vec::new();
+				while lengths.len() < literal_lengths + distances {
+					let (length, repeat) = match code_length.decode(reader)? {
+						16 => (*lengths.last().ok_or(CORRUPT)?, 3 + reader.read(2)?),
+						17 => (0, 3 + reader.read(3)?),
+						18 => (0, 11 + reader.read(7)?),
+						length => (length as u8, 1)
+					};
+					lengths.extend((0..repeat).map(|_| length));
+				}
+				if lengths.len() != literal_lengths + distances {
+					return Err(CORRUPT);
+				}
+
+				let (literal_length, distance) = lengths.split_at(literal_lengths);
+				inflate_block(reader, &Decoder::new(literal_length), &Decoder::new(distance), &mut out, limit)?;
+			},
+			_ => return Err(CORRUPT)
+		}
+
+		if last {
+			return Ok(out);
+		}
+	}
+}
+
+fn inflate_block (reader: &mut BitReader, literal_length: &Decoder, distance: &Decoder, out: &mut Vec<u8>, limit: usize) -> Result<(), Error> {
+	loop {
+		if out.len() > limit {
+			return Err(CORRUPT);
+		}
+		match literal_length.decode(reader)? {
+			byte @ 0..=255 => {
+				reserve(out, 1)?;
+				out.push(byte as u8);
+			},
+			END_OF_BLOCK => return Ok(()),
+			symbol => {
+				let symbol = symbol - 257;
+				let length = *LENGTH_BASE.get(symbol).ok_or(CORRUPT)? as usize + reader.read(*LENGTH_EXTRA.get(symbol).ok_or(CORRUPT)? as u32)? as usize;
+				let symbol = distance.decode(reader)?;
+				let offset = *DISTANCE_BASE.get(symbol).ok_or(CORRUPT)? as usize + reader.read(*DISTANCE_EXTRA.get(symbol).ok_or(CORRUPT)? as u32)? as usize;
+
+				let start = out.len().checked_sub(offset).ok_or(CORRUPT)?;
+				reserve(out, length)?;
+				for i in 0..length {
+					out.push(out[start + i]);
+				}
+			}
+		}
+	}
+}
+
+
+#[cfg(test)]
+mod tests {
+
+	use super::{ zlib, zlib_with, unzlib, adler32, lz77, code_lengths, run_lengths, Compression, Strategy, Token, ZlibEncoder };
+
+	#[cfg(not(feature = "std"))]
+	use alloc::{ vec, vec::Vec };
+
+
+
+	// deterministic bytes that don't compress
+	fn noise (len: usize) -> Vec<u8> {
+		let mut state = 0x2545f4914f6cdd1du64;
+		(0..len).map(|_| {
+			state ^= state << 13;
+			state ^= state >> 7;
+			state ^= state << 17;
+			(state >> 32) as u8
+		}).collect()
+	}
+
+	#[test]
+	fn test_adler32 () {
+		assert_eq!(adler32(b""), 1);
+		assert_eq!(adler32(b"Wikipedia"), 0x11e60398);
+		assert_eq!(adler32(&[255; 100_000]), 0x149a_302c);
+	}
+
+	#[test]
+	fn test_round_trip () {
+		let text = b"the quick brown fox jumps over the lazy dog, the quick brown fox jumps over the lazy dog".to_vec();
+		let rows = (0..200u32).flat_map(|y| (0..64u32).map(move |x| (x * y) as u8)).collect::<Vec<_>>();
+
+		for data in [vec![], vec![0], text, rows, vec![0; 300_000], noise(70_000), [noise(1000), vec![7; 5000], noise(1000)].concat()] {
+			let compressed = zlib(&data);
+			assert_eq!(unzlib(&compressed).as_deref(), Some(&data[..]), "{} bytes didn't round trip", data.len());
+		}
+	}
+
+	#[test]
+	fn test_encoder () {
+		// repeats of a block of noise, each repeat only matching the one
+		// before it from the window kept between segments
+		let data = noise(20_000).repeat(20);
+
+		for (compression, strategy) in [(Compression::default(), Strategy::Default), (Compression::Level(1), Strategy::Rle), (Compression::Level(0), Strategy::Default)] {
+			let mut encoder = ZlibEncoder::new(compression, strategy);
+			let mut compressed = Vec::new();
+			for piece in data.chunks(7919) {
+				encoder.write(piece);
+				compressed.extend(encoder.output());
+			}
+			compressed.extend(encoder.finish());
+
+			assert_eq!(unzlib(&compressed).as_deref(), Some(&data[..]), "{compression:?} {strategy:?} didn't round trip");
+			if compression == Compression::default() {
+				assert!(compressed.len() < 30_000, "{} bytes", compressed.len());
+			}
+		}
+
+		let mut encoder = ZlibEncoder::new(Compression::default(), Strategy::Default);
+		assert_eq!(encoder.finish(), zlib(b""));
+	}
+
+	#[test]
+	fn test_compression_ratio () {
+		// a 1000x1000 solid rgba image with a filter byte per row
+		let solid = [[0].as_slice(), &[235, 35, 35, 255].repeat(1000)].concat().repeat(1000);
+		assert!(zlib(&solid).len() < solid.len() / 500);
+
+		// incompressible data is stored, only growing by a few block
+		// headers and the zlib wrapper
+		assert!(zlib(&noise(100_000)).len() < 100_000 + 64);
+	}
+
+	#[test]
+	fn test_lz77 () {
+		let effort = Compression::default().effort().unwrap();
+		assert_eq!(lz77(b"abcabcabcd", 0, effort, Strategy::Default), [
+			Token::Literal(b'a'),
+			Token::Literal(b'b'),
+			Token::Literal(b'c'),
+			Token::Match { length: 6, distance: 3 },
+			Token::Literal(b'd')
+		]);
+
+		let tokens = lz77(&[1; 1000], 0, effort, Strategy::Default);
+		assert_eq!(tokens[..2], [Token::Literal(1), Token::Match { length: 258, distance: 1 }]);
+		assert_eq!(tokens.iter().map(Token::len).sum::<usize>(), 1000);
+	}
+
+	#[test]
+	fn test_strategies () {
+		let effort = Compression::default().effort().unwrap();
+		let data = b"abcabcabcdddddd";
+
+		assert!(lz77(data, 0, effort, Strategy::HuffmanOnly).iter().all(|token| matches!(token, Token::Literal(_))));
+		assert_eq!(lz77(data, 0, effort, Strategy::Rle)[9..], [Token::Literal(b'd'), Token::Match { length: 5, distance: 1 }]);
+		// the 6 byte match is long enough to keep, the run of 5 isn't
+		assert_eq!(lz77(data, 0, effort, Strategy::Filtered)[3..], [
+			Token::Match { length: 6, distance: 3 },
+			Token::Literal(b'd'),
+			Token::Literal(b'd'),
+			Token::Literal(b'd'),
+			Token::Literal(b'd'),
+			Token::Literal(b'd'),
+			Token::Literal(b'd')
+		]);
+	}
+
+	#[test]
+	fn test_levels () {
+		let rows = (0..200u32).flat_map(|y| (0..64u32).map(move |x| (x * y / 7) as u8)).collect::<Vec<_>>();
+
+		let mut sizes = Vec::new();
+		for compression in (0..=10).map(Compression::Level).chain([Compression::Max]) {
+			for strategy in [Strategy::Default, Strategy::Filtered, Strategy::Rle, Strategy::HuffmanOnly, Strategy::Stored] {
+				let compressed = zlib_with(&rows, compression, strategy);
+				assert_eq!(unzlib(&compressed).as_deref(), Some(&rows[..]), "{compression:?} {strategy:?} didn't round trip");
+			}
+			sizes.push(zlib_with(&rows, compression, Strategy::Default).len());
+		}
+
+		// stored, then smaller with more effort
+		assert_eq!(sizes[0], rows.len() + 2 + 5 + 4);
+		assert!(sizes[1] < sizes[0] && sizes[6] <= sizes[1] && sizes[11] <= sizes[6], "{sizes:?}");
+		// only the header's level differs
+		assert_eq!(zlib_with(&rows, Compression::Level(6), Strategy::Stored)[2..], zlib_with(&rows, Compression::Level(0), Strategy::Default)[2..]);
+	}
+
+	#[test]
+	fn test_zlib_header () {
+		let header = |compression| zlib_with(b"", compression, Strategy::Default)[..2].to_vec();
+		assert_eq!(header(Compression::Level(0)), [0x78, 0x01]);
+		assert_eq!(header(Compression::Level(3)), [0x78, 0x5e]);
+		assert_eq!(header(Compression::Level(6)), [0x78, 0x9c]);
+		assert_eq!(header(Compression::Max), [0x78, 0xda]);
+	}
+
+	#[test]
+	fn test_code_lengths () {
+		assert_eq!(code_lengths(&[1, 1, 2, 4], 15), [3, 3, 2, 1]);
+		assert_eq!(code_lengths(&[0, 5, 0], 15), [1, 1, 0]);
+		assert_eq!(code_lengths(&[0, 0], 15), [0, 0]);
+
+		// fibonacci frequencies make the deepest possible tree
+		let mut fibonacci = vec![1u32, 1];
+		while fibonacci.len() < 30 {
+			fibonacci.push(fibonacci[fibonacci.len() - 1] + fibonacci[fibonacci.len() - 2]);
+		}
+		let lengths = code_lengths(&fibonacci, 15);
+		assert!(lengths.iter().all(|&length| length <= 15));
+		// still a complete code
+		assert_eq!(lengths.iter().map(|&length| 1.0 / (1u32 << length) as f64).sum::<f64>(), 1.0);
+	}
+
+	#[test]
+	fn test_run_lengths () {
+		assert_eq!(run_lengths(&[3, 3, 3, 3, 3, 0, 0]), [(3, 0), (16, 1), (0, 0), (0, 0)]);
+		assert_eq!(run_lengths(&[0; 150]), [(18, 127), (18, 1)]);
+		assert_eq!(run_lengths(&[0; 14]), [(18, 3)]);
+		assert_eq!(run_lengths(&[5; 9]), [(5, 0), (16, 3), (5, 0), (5, 0)]);
+	}
+
+}
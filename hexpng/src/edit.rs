@@ -0,0 +1,248 @@
+// editing the ancillary chunks of existing pngs, without touching the
+// image data
+
+use crate::{ Chunk, Error, Text };
+use crate::{ decode, png, validate };
+
+#[cfg(not(feature = "std"))]
+use alloc::{ borrow::Cow, vec::Vec };
+#[cfg(feature = "std")]
+use std::borrow::Cow;
+
+
+
+// chunks that could tell who made a png, and when and where
+const METADATA: [&[u8; 4]; 5] = [b"tEXt", b"zTXt", b"iTXt", b"tIME", b"eXIf"];
+
+
+
+/// the chunks of an existing png, to remove, add or replace ancillary
+/// chunks and write it back out with fresh crcs
+///
+/// critical chunks like the image data are passed through untouched,
+/// and added chunks are put where the png spec allows them
+///
+/// ```
+/// use hexpng::{ PngEditor, PngOptions, Text, chunks };
+///
+/// let png = PngOptions::new()
+///     .text(Text::new("Author", "someone").unwrap())
+///     .generate(235, 35, 35, 255)
+///     .unwrap();
+///
+/// let mut editor = PngEditor::parse(&png).unwrap();
+/// editor.strip_metadata();
+/// editor.text(&Text::new("Software", "hexpng").unwrap()).unwrap();
+/// let png = editor.to_png().unwrap();
+///
+/// let text = chunks(&png).unwrap().map(|chunk| chunk.unwrap()).find(|chunk| &chunk.chunk_type == b"tEXt");
+/// assert_eq!(text.unwrap().data, b"Software\0hexpng");
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct PngEditor<'a> {
+	// types and data of the chunks from IHDR to IEND
+	chunks: Vec<([u8; 4], Cow<'a, [u8]>)>
+}
+
+impl<'a> PngEditor<'a> {
+
+	/// read the chunks of a png, checking their crcs
+	///
+	/// fails with [`Error::InvalidPng`] if a chunk is corrupt or the png
+	/// doesn't start with IHDR and end with IEND, anything after IEND is
+	/// dropped
+	pub fn parse (png: &'a [u8]) -> Result<Self, Error> {
+		let mut chunks = Vec::new();
+		for chunk in decode::chunks(png)? {
+			let chunk = chunk?;
+			chunks.push((chunk.chunk_type, Cow::Borrowed(chunk.data)));
+			if &chunk.chunk_type == b"IEND" {
+				break;
+			}
+		}
+
+		if chunks.first().map(|(chunk_type, _)| chunk_type) != Some(b"IHDR") || chunks.last().map(|(chunk_type, _)| chunk_type) != Some(b"IEND") {
+			return Err(Error::InvalidPng("the png has to start with IHDR and end with IEND"));
+		}
+		Ok(Self { chunks })
+	}
+
+	/// the chunks in order
+	pub fn chunks (&self) -> impl Iterator<Item = Chunk<'_>> {
+		self.chunks.iter().map(|(chunk_type, data)| Chunk { chunk_type: *chunk_type, data })
+	}
+
+	/// keep only the ancillary chunks `keep` returns true for, critical
+	/// chunks are always kept
+	pub fn retain (&mut self, mut keep: impl FnMut(&Chunk) -> bool) -> &mut Self {
+		self.chunks.retain(|(chunk_type, data)| {
+			let chunk = Chunk { chunk_type: *chunk_type, data };
+			chunk.is_critical() || keep(&chunk)
+		});
+		self
+	}
+
+	/// remove every ancillary chunk of `chunk_type`
+	pub fn remove (&mut self, chunk_type: &[u8; 4]) -> &mut Self {
+		self.retain(|chunk| &chunk.chunk_type != chunk_type)
+	}
+
+	/// remove text, timestamps and exif data, which can give away who
+	/// made the png and when
+	///
+	/// color space chunks like an embedded icc profile are kept, since
+	/// they change how the pixels look, [`remove`](Self::remove) them
+	/// explicitly if they have to go
+	pub fn strip_metadata (&mut self) -> &mut Self {
+		self.retain(|chunk| !METADATA.contains(&&chunk.chunk_type))
+	}
+
+	/// add an ancillary chunk, before the image data, or before PLTE for
+	/// chunks that have to come before it like `gAMA`
+	///
+	/// fails with [`Error::InvalidChunk`] if the type isn't a valid
+	/// ancillary chunk type, the data is too long, or the png already has
+	/// a chunk of the type and can only have one, which
+	/// [`replace`](PngEditor::replace) is for
+	pub fn insert (&mut self, chunk_type: [u8; 4], data: Vec<u8>) -> Result<&mut Self, Error> {
+		if !chunk_type.iter().all(u8::is_ascii_alphabetic) || !chunk_type[2].is_ascii_uppercase() {
+			return Err(Error::InvalidChunk("chunk types have to be 4 ascii letters, with the third uppercase"));
+		}
+		if chunk_type[0].is_ascii_uppercase() {
+			return Err(Error::InvalidChunk("only ancillary chunks can be added"));
+		}
+		if data.len() > png::MAX_CHUNK_LEN as usize {
+			return Err(Error::InvalidChunk("chunks can't hold more than 2^31-1 bytes"));
+		}
+		if validate::UNIQUE.contains(&&chunk_type) && self.chunks.iter().any(|(other, _)| *other == chunk_type) {
+			return Err(Error::InvalidChunk("the png can only have one chunk of this type"));
+		}
+
+		let before: &[&[u8; 4]] = if validate::BEFORE_PLTE.contains(&&chunk_type) { &[b"PLTE", b"IDAT"] } else { &[b"IDAT"] };
+		let index = self.chunks.iter()
+			.position(|(other, _)| before.contains(&other))
+			.unwrap_or(self.chunks.len() - 1);
+		self.chunks.insert(index, (chunk_type, Cow::Owned(data)));
+		Ok(self)
+	}
+
+	/// remove the ancillary chunks of `chunk_type` and add one with
+	/// `data` instead, fails like [`insert`](PngEditor::insert)
+	pub fn replace (&mut self, chunk_type: [u8; 4], data: Vec<u8>) -> Result<&mut Self, Error> {
+		self.remove(&chunk_type).insert(chunk_type, data)
+	}
+
+	/// add text, replacing any text with the same keyword
+	pub fn text (&mut self, text: &Text) -> Result<&mut Self, Error> {
+		let data = text.chunk_data()?;
+		let keyword = data.split(|&byte| byte == 0).next().unwrap_or_default();
+
+		self.retain(|chunk| {
+			!(matches!(&chunk.chunk_type, b"tEXt" | b"zTXt" | b"iTXt") && chunk.data.split(|&byte| byte == 0).next() == Some(keyword))
+		});
+		self.insert(*text.chunk_type(), data)
+	}
+
+	/// write the png back out, with every chunk's crc recomputed
+	pub fn to_png (&self) -> Result<Vec<u8>, Error> {
+		let mut png = png::HEADER.to_vec();
+		for (chunk_type, data) in &self.chunks {
+			png.extend(png::chunk(chunk_type, data)?);
+		}
+		Ok(png)
+	}
+
+}
+
+
+
+#[cfg(test)]
+mod tests {
+
+	use super::PngEditor;
+	use crate::{ PngOptions, Error, IccProfile, Text, Timestamp, chunks, decode, validate };
+
+	#[cfg(not(feature = "std"))]
+	use alloc::vec::Vec;
+
+
+
+	fn chunk_types (png: &[u8]) -> Vec<[u8; 4]> {
+		chunks(png).unwrap().map(|chunk| chunk.unwrap().chunk_type).collect()
+	}
+
+	#[test]
+	fn test_round_trip () {
+		let png = PngOptions::new().dimensions(20, 10).tag_srgb().generate(235, 35, 35, 127).unwrap();
+		assert_eq!(PngEditor::parse(&png).unwrap().to_png().unwrap(), png);
+
+		// trailing data is dropped
+		let trailing = [png.clone(), b"garbage".to_vec()].concat();
+		assert_eq!(PngEditor::parse(&trailing).unwrap().to_png().unwrap(), png);
+
+		let mut corrupt = png.clone();
+		let last = corrupt.len() - 13;
+		corrupt[last] ^= 1;
+		assert!(matches!(PngEditor::parse(&corrupt), Err(Error::InvalidPng(_))));
+		assert_eq!(PngEditor::parse(&png[..png.len() - 12]), Err(Error::InvalidPng("the png has to start with IHDR and end with IEND")));
+	}
+
+	#[test]
+	fn test_strip () {
+		let png = PngOptions::new()
+			.text(Text::new("Author", "someone").unwrap())
+			.text(Text::international("Title", "rot", "de", "Titel").unwrap())
+			.modified(Timestamp::new(2024, 1, 1, 0, 0, 0).unwrap())
+			.tag_srgb()
+			.generate(235, 35, 35, 255)
+			.unwrap();
+
+		let mut editor = PngEditor::parse(&png).unwrap();
+		let stripped = editor.strip_metadata().to_png().unwrap();
+		assert_eq!(chunk_types(&stripped), [*b"IHDR", *b"cHRM", *b"gAMA", *b"sRGB", *b"IDAT", *b"IEND"]);
+		assert_eq!(decode(&stripped).unwrap(), decode(&png).unwrap());
+
+		// an icc profile isn't metadata, it decides what the colors are
+		let profile = PngOptions::new()
+			.icc_profile(IccProfile::new("Display P3", b"profile").unwrap())
+			.text(Text::new("Author", "someone").unwrap())
+			.generate(235, 35, 35, 255)
+			.unwrap();
+		let kept = PngEditor::parse(&profile).unwrap().strip_metadata().to_png().unwrap();
+		assert_eq!(chunk_types(&kept), [*b"IHDR", *b"iCCP", *b"IDAT", *b"IEND"]);
+
+		// critical chunks can't be removed
+		let bare = editor.retain(|_| false).remove(b"IDAT").to_png().unwrap();
+		assert_eq!(chunk_types(&bare), [*b"IHDR", *b"IDAT", *b"IEND"]);
+		assert!(validate(&bare).is_valid());
+	}
+
+	#[test]
+	fn test_insert () {
+		let png = PngOptions::new().idat_size(8).generate(235, 35, 35, 255).unwrap();
+		let mut editor = PngEditor::parse(&png).unwrap();
+
+		editor.insert(*b"gAMA", 45455u32.to_be_bytes().to_vec()).unwrap();
+		editor.insert(*b"tRNS", [0].to_vec()).unwrap();
+		editor.text(&Text::new("Comment", "first").unwrap()).unwrap();
+		editor.text(&Text::new("Comment", "second").unwrap().compressed(true)).unwrap();
+		editor.text(&Text::new("Software", "hexpng").unwrap()).unwrap();
+
+		let edited = editor.to_png().unwrap();
+		let types = chunk_types(&edited);
+		assert_eq!(types[..5], [*b"IHDR", *b"gAMA", *b"PLTE", *b"tRNS", *b"zTXt"]);
+		assert_eq!(types[5], *b"tEXt");
+		assert_eq!(validate(&edited).diagnostics, []);
+		assert_eq!(decode(&edited).unwrap().pixels[..4], [235, 35, 35, 0]);
+
+		assert_eq!(editor.insert(*b"gAMA", Vec::new()), Err(Error::InvalidChunk("the png can only have one chunk of this type")));
+		assert_eq!(editor.insert(*b"IDAT", Vec::new()), Err(Error::InvalidChunk("only ancillary chunks can be added")));
+		assert!(editor.insert(*b"te1t", Vec::new()).is_err());
+		assert!(editor.insert(*b"text", Vec::new()).is_err());
+
+		editor.replace(*b"gAMA", 100000u32.to_be_bytes().to_vec()).unwrap();
+		let gamma = editor.chunks().filter(|chunk| &chunk.chunk_type == b"gAMA").map(|chunk| chunk.data).collect::<Vec<_>>();
+		assert_eq!(gamma, [100000u32.to_be_bytes()]);
+	}
+
+}
@@ -0,0 +1,79 @@
+use core::fmt;
+
+use crate::ParseColorError;
+
+
+
+/// everything that can go wrong while generating, reading or editing a png
+///
+/// new variants can be added as hexpng grows, so matches need a
+/// wildcard arm
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Error {
+	/// the zlib encoder failed to compress the image data
+	Compression,
+	/// width or height is zero or larger than [`MAX_DIMENSION`](crate::MAX_DIMENSION)
+	InvalidDimensions { width: u32, height: u32 },
+	/// the raw image data wouldn't fit in memory
+	ImageTooLarge,
+	/// a color string couldn't be parsed
+	InvalidColor(ParseColorError),
+	/// the requested options can't be used together
+	UnsupportedOptions(&'static str),
+	/// caller supplied pixels don't describe a valid image
+	InvalidImage(&'static str),
+	/// data for an ancillary chunk isn't allowed by the png spec
+	InvalidChunk(&'static str),
+	/// a png being decoded is corrupt or uses something hexpng can't read
+	InvalidPng(&'static str),
+	/// writing the png to a [`Sink`](crate::Sink) failed, and why
+	Io(&'static str),
+	/// writing the png to a [`std::io::Write`] failed, with the kind of
+	/// error and the os error code if there is one
+	#[cfg(feature = "std")]
+	StdIo { kind: std::io::ErrorKind, os_error: Option<i32> }
+}
+
+impl fmt::Display for Error {
+	fn fmt (&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			Self::Compression => write!(f, "failed to compress the image data"),
+			Self::InvalidDimensions { width, height } => write!(f, "invalid png dimensions {}x{}", width, height),
+			Self::ImageTooLarge => write!(f, "the image data doesn't fit in memory"),
+			Self::InvalidColor(e) => write!(f, "invalid color: {}", e),
+			Self::UnsupportedOptions(reason) => write!(f, "unsupported options: {}", reason),
+			Self::InvalidImage(reason) => write!(f, "invalid image: {}", reason),
+			Self::InvalidChunk(reason) => write!(f, "invalid chunk: {}", reason),
+			Self::InvalidPng(reason) => write!(f, "invalid png: {}", reason),
+			Self::Io(reason) => write!(f, "failed to write the png: {}", reason),
+			#[cfg(feature = "std")]
+			Self::StdIo { kind, os_error: Some(code) } => write!(f, "failed to write the png: {} (os error {})", kind, code),
+			#[cfg(feature = "std")]
+			Self::StdIo { kind, os_error: None } => write!(f, "failed to write the png: {}", kind)
+		}
+	}
+}
+
+impl From<ParseColorError> for Error {
+	fn from (e: ParseColorError) -> Self {
+		Self::InvalidColor(e)
+	}
+}
+
+#[cfg(feature = "std")]
+impl From<std::io::Error> for Error {
+	fn from (e: std::io::Error) -> Self {
+		Self::StdIo { kind: e.kind(), os_error: e.raw_os_error() }
+	}
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for Error {
+	fn source (&self) -> Option<&(dyn std::error::Error + 'static)> {
+		match self {
+			Self::InvalidColor(e) => Some(e),
+			_ => None
+		}
+	}
+}
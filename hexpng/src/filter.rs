@@ -0,0 +1,235 @@
+use crate::Error;
+
+#[cfg(not(feature = "std"))]
+use alloc::{ vec, vec::Vec };
+
+
+
+/// how scanlines are filtered before being compressed
+///
+/// filtering doesn't change the pixels, but turns smooth images into
+/// lots of small repeating values that compress much better
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum Filter {
+	/// store the bytes as they are
+	#[default]
+	None = 0,
+	/// difference from the pixel to the left
+	Sub = 1,
+	/// difference from the pixel above
+	Up = 2,
+	/// difference from the average of the pixels to the left and above
+	Average = 3,
+	/// difference from whichever of left, above or upper left is closest
+	/// to left + above - upper left
+	Paeth = 4,
+	/// pick the filter for each scanline with the minimum sum of absolute
+	/// differences, the heuristic recommended by the png spec
+	Adaptive,
+	/// compress with each of the other filters and keep the smallest,
+	/// slow but never worse than any of them
+	BruteForce
+}
+
+impl Filter {
+
+	/// the filters [`Filter::BruteForce`] tries
+	pub(crate) const BRUTE_FORCE: [Self; 6] = [Self::None, Self::Sub, Self::Up, Self::Average, Self::Paeth, Self::Adaptive];
+
+	const TYPES: [Self; 5] = [Self::None, Self::Sub, Self::Up, Self::Average, Self::Paeth];
+
+}
+
+
+
+// filter every scanline of `data` in place, a scanline being a filter
+// byte followed by `row_len` bytes, `bpp` is the number of bytes per
+// complete pixel rounded up to 1
+//
+// `Filter::BruteForce` has to be handled by the caller, it's treated
+// like `Filter::Adaptive` here
+pub(crate) fn apply (filter: Filter, data: &mut [u8], row_len: usize, bpp: usize) {
+	let stride = row_len + 1;
+	let mut row_filter = RowFilter::new(filter, row_len, bpp);
+
+	// bottom up, so the row above is still unfiltered when it's needed
+	for y in (0..data.len() / stride).rev() {
+		let (above, current) = data.split_at_mut(y * stride);
+		let previous = y.checked_sub(1).map(|_| &above[above.len() - row_len..]);
+		row_filter.apply(&mut current[..stride], previous);
+	}
+}
+
+
+
+// filters scanlines one at a time, keeping the space for the filtered
+// candidates between them
+pub(crate) struct RowFilter {
+	filter: Filter,
+	bpp: usize,
+	candidates: Vec<Vec<u8>>
+}
+
+impl RowFilter {
+
+	pub(crate) fn new (filter: Filter, row_len: usize, bpp: usize) -> Self {
+		let candidates = match filter {
+			Filter::Adaptive | Filter::BruteForce => vec![vec![0u8; row_len]; Filter::TYPES.len()],
+			_ => vec![vec![0u8; row_len]]
+		};
+		Self { filter, bpp, candidates }
+	}
+
+	// filter `row`, a filter byte followed by `row_len` bytes, in place,
+	// `previous` being the unfiltered bytes of the row above
+	pub(crate) fn apply (&mut self, row: &mut [u8], previous: Option<&[u8]>) {
+		let candidates = &mut self.candidates;
+
+		let chosen = match self.filter {
+			Filter::Adaptive | Filter::BruteForce => {
+				for (filter, candidate) in Filter::TYPES.iter().zip(candidates.iter_mut()) {
+					filter_row(*filter, &row[1..], previous, self.bpp, candidate);
+				}
+				(0..candidates.len())
+					.min_by_key(|&i| candidates[i].iter().map(|&byte| (byte as i8).unsigned_abs() as u64).sum::<u64>())
+					.unwrap_or(0)
+			},
+			filter => {
+				filter_row(filter, &row[1..], previous, self.bpp, &mut candidates[0]);
+				0
+			}
+		};
+
+		row[0] = match self.filter {
+			Filter::Adaptive | Filter::BruteForce => chosen as u8,
+			filter => filter as u8
+		};
+		row[1..].copy_from_slice(&candidates[chosen]);
+	}
+
+}
+
+
+
+fn filter_row (filter: Filter, row: &[u8], previous: Option<&[u8]>, bpp: usize, out: &mut [u8]) {
+	for (i, out) in out.iter_mut().enumerate() {
+		let (a, b, c) = neighbours(row, previous, bpp, i);
+
+		*out = row[i].wrapping_sub(match filter {
+			Filter::Sub => a,
+			Filter::Up => b,
+			Filter::Average => ((a as u16 + b as u16) / 2) as u8,
+			Filter::Paeth => paeth(a, b, c),
+			_ => 0
+		});
+	}
+}
+
+// the unfiltered bytes to the left, above and upper left of `row[i]`
+fn neighbours (row: &[u8], previous: Option<&[u8]>, bpp: usize, i: usize) -> (u8, u8, u8) {
+	let a = i.checked_sub(bpp).map_or(0, |left| row[left]);
+	let b = previous.map_or(0, |previous| previous[i]);
+	let c = i.checked_sub(bpp).zip(previous).map_or(0, |(left, previous)| previous[left]);
+	(a, b, c)
+}
+
+fn paeth (a: u8, b: u8, c: u8) -> u8 {
+	let p = a as i16 + b as i16 - c as i16;
+	let (pa, pb, pc) = ((p - a as i16).abs(), (p - b as i16).abs(), (p - c as i16).abs());
+
+	if pa <= pb && pa <= pc {
+		a
+	} else if pb <= pc {
+		b
+	} else {
+		c
+	}
+}
+
+
+
+// reverse `apply`, leaving every filter byte as 0
+pub(crate) fn unfilter (data: &mut [u8], row_len: usize, bpp: usize) -> Result<(), Error> {
+	let stride = row_len + 1;
+
+	for y in 0..data.len() / stride {
+		let (above, current) = data.split_at_mut(y * stride);
+		let previous = y.checked_sub(1).map(|_| &above[above.len() - row_len..]);
+		let row = &mut current[..stride];
+		let filter = *Filter::TYPES.get(row[0] as usize).ok_or(Error::InvalidPng("unknown filter type"))?;
+		row[0] = 0;
+
+		// left to right, so the bytes to the left are already unfiltered
+		for i in 0..row_len {
+			let (a, b, c) = neighbours(&row[1..], previous, bpp, i);
+			row[1 + i] = row[1 + i].wrapping_add(match filter {
+				Filter::Sub => a,
+				Filter::Up => b,
+				Filter::Average => ((a as u16 + b as u16) / 2) as u8,
+				Filter::Paeth => paeth(a, b, c),
+				_ => 0
+			});
+		}
+	}
+
+	Ok(())
+}
+
+
+
+#[cfg(test)]
+mod tests {
+
+	use super::{ Filter, apply, unfilter };
+
+	#[cfg(not(feature = "std"))]
+	use alloc::vec::Vec;
+
+
+
+	// a few rows of a smooth gradient with some noise
+	fn scanlines (row_len: usize, rows: usize) -> Vec<u8> {
+		(0..rows).flat_map(|y| {
+			[0].into_iter().chain((0..row_len).map(move |x| (x * 3 + y * 7 + (x * y) % 5) as u8))
+		}).collect()
+	}
+
+	#[test]
+	fn test_round_trip () {
+		for filter in Filter::BRUTE_FORCE {
+			for (row_len, bpp) in [(1, 1), (12, 3), (32, 4), (7, 2), (16, 8)] {
+				let raw = scanlines(row_len, 5);
+				let mut data = raw.clone();
+
+				apply(filter, &mut data, row_len, bpp);
+				if filter != Filter::None {
+					assert_ne!(data, raw, "{:?} didn't filter anything", filter);
+				}
+
+				unfilter(&mut data, row_len, bpp).unwrap();
+				assert_eq!(data, raw, "{:?} didn't round trip with {} bytes per pixel", filter, bpp);
+			}
+		}
+	}
+
+	#[test]
+	fn test_filter_bytes () {
+		let raw = scanlines(12, 4);
+
+		for filter in [Filter::Sub, Filter::Up, Filter::Average, Filter::Paeth] {
+			let mut data = raw.clone();
+			apply(filter, &mut data, 12, 3);
+			assert!(data.iter().step_by(13).all(|&byte| byte == filter as u8));
+		}
+
+		let mut data = raw.clone();
+		apply(Filter::Adaptive, &mut data, 12, 3);
+		assert!(data.iter().step_by(13).all(|&byte| byte <= 4));
+
+		// a smooth gradient filters to all the same differences
+		let mut data = [0, 10, 20, 30, 40].to_vec();
+		apply(Filter::Sub, &mut data, 4, 1);
+		assert_eq!(data, [1, 10, 10, 10, 10]);
+	}
+
+}
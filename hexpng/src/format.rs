@@ -0,0 +1,435 @@
+use crate::Error;
+
+#[cfg(not(feature = "std"))]
+use alloc::{ vec, vec::Vec };
+
+
+
+/// the color types a png can be stored as
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ColorType {
+	/// a single gray channel, bit depths 1, 2, 4, 8 or 16
+	Grayscale = 0,
+	/// red, green and blue channels, bit depths 8 or 16
+	Rgb = 2,
+	/// an index into a palette, bit depths 1, 2, 4 or 8
+	Indexed = 3,
+	/// a gray and an alpha channel, bit depths 8 or 16
+	GrayscaleAlpha = 4,
+	/// red, green, blue and alpha channels, bit depths 8 or 16
+	Rgba = 6
+}
+
+impl ColorType {
+
+	/// number of samples per pixel
+	pub fn channels (self) -> usize {
+		match self {
+			Self::Grayscale | Self::Indexed => 1,
+			Self::GrayscaleAlpha => 2,
+			Self::Rgb => 3,
+			Self::Rgba => 4
+		}
+	}
+
+	/// whether the png spec allows this color type with `bit_depth`
+	pub fn allows_bit_depth (self, bit_depth: u8) -> bool {
+		match self {
+			Self::Grayscale => matches!(bit_depth, 1 | 2 | 4 | 8 | 16),
+			Self::Indexed => matches!(bit_depth, 1 | 2 | 4 | 8),
+			Self::Rgb | Self::GrayscaleAlpha | Self::Rgba => matches!(bit_depth, 8 | 16)
+		}
+	}
+
+	/// number of bits a single pixel takes
+	pub fn bits_per_pixel (self, bit_depth: u8) -> usize {
+		self.channels() * bit_depth as usize
+	}
+
+}
+
+
+
+// how the pixels of an image are stored, with the palette and
+// transparency needed to losslessly reconstruct the original rgba8
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Format {
+	pub color_type: ColorType,
+	pub bit_depth: u8,
+	// rgba palette for `ColorType::Indexed`, translucent entries first
+	// so the tRNS chunk can stop at the last translucent one
+	pub palette: Vec<[u8; 4]>,
+	// the single fully transparent rgb color of grayscale/rgb images
+	pub transparent: Option<[u8; 3]>
+}
+
+impl Format {
+
+	// the formats that might store an rgba8 image the smallest, which
+	// one actually does depends on how well each compresses
+	//
+	// `pixels` must contain every distinct pixel of the image at least
+	// once, but doesn't have to be the whole image
+	pub fn candidates (pixels: &[u8]) -> Vec<Self> {
+		let stats = Stats::collect(pixels);
+
+		// grayscale stores less than grayscale+alpha, which stores less
+		// than rgb and so on, so only the first that works is a candidate
+		let truecolor = [
+			(ColorType::Grayscale, stats.gray_depth),
+			(ColorType::GrayscaleAlpha, 8),
+			(ColorType::Rgb, 8),
+			(ColorType::Rgba, 8)
+		].into_iter().find_map(|(color_type, bit_depth)| stats.format(color_type, bit_depth));
+
+		truecolor.into_iter()
+			.chain(stats.format(ColorType::Indexed, stats.palette_depth()))
+			.collect()
+	}
+
+	// the smallest indexed format for the rgba8 image, if it has at most
+	// 256 distinct pixels
+	pub fn indexed (pixels: &[u8]) -> Option<Self> {
+		let stats = Stats::collect(pixels);
+		stats.format(ColorType::Indexed, stats.palette_depth())
+	}
+
+	// check that the rgba8 image can be stored losslessly with `color_type` and `bit_depth`
+	pub fn requested (pixels: &[u8], color_type: ColorType, bit_depth: u8) -> Result<Self, Error> {
+		if !color_type.allows_bit_depth(bit_depth) {
+			return Err(Error::UnsupportedOptions("bit depth isn't allowed for the color type"));
+		}
+
+		Stats::collect(pixels).format(color_type, bit_depth)
+			.ok_or(Error::UnsupportedOptions("the image can't be stored losslessly with the requested color type and bit depth"))
+	}
+
+	// check the png spec allows the color type with the bit depth, and
+	// that indexed images, and only those, have a palette that fits
+	pub fn validate (&self) -> Result<(), Error> {
+		if !self.color_type.allows_bit_depth(self.bit_depth) {
+			return Err(Error::UnsupportedOptions("bit depth isn't allowed for the color type"));
+		}
+
+		match self.color_type {
+			ColorType::Indexed if self.palette.is_empty() || self.palette.len() > 1 << self.bit_depth => {
+				Err(Error::UnsupportedOptions("indexed images need a palette with 1 to 2^bit depth entries"))
+			},
+			ColorType::Indexed => Ok(()),
+			_ if !self.palette.is_empty() => Err(Error::UnsupportedOptions("only indexed images can have a palette")),
+			_ => Ok(())
+		}
+	}
+
+	// bytes in a scanline without the filter byte
+	pub fn row_len (&self, width: u32) -> Option<usize> {
+		usize::try_from(width).ok()?
+			.checked_mul(self.color_type.bits_per_pixel(self.bit_depth))?
+			.checked_add(7)
+			.map(|bits| bits / 8)
+	}
+
+	// scale an 8 bit sample down to the bit depth, only exact for
+	// samples that `sample_depth` says fit
+	fn scale (&self, sample: u8) -> u16 {
+		match self.bit_depth {
+			16 => sample as u16 * 257,
+			depth => sample as u16 / (255 / ((1 << depth) - 1))
+		}
+	}
+
+	// convert a row of rgba8 pixels, appending the packed samples to `out`
+	pub fn pack_row (&self, rgba: &[u8], out: &mut Vec<u8>) {
+		let mut packer = BitPacker::new(self.bit_depth, out);
+		let (translucent, opaque) = self.palette.split_at(self.palette.iter().take_while(|entry| entry[3] != 255).count());
+
+		for pixel in rgba.chunks_exact(4) {
+			let [r, g, b, a] = [pixel[0], pixel[1], pixel[2], pixel[3]];
+
+			match self.color_type {
+				ColorType::Grayscale => packer.push(self.scale(r)),
+				ColorType::GrayscaleAlpha => {
+					packer.push(self.scale(r));
+					packer.push(self.scale(a));
+				},
+				ColorType::Rgb => {
+					packer.push(self.scale(r));
+					packer.push(self.scale(g));
+					packer.push(self.scale(b));
+				},
+				ColorType::Rgba => {
+					packer.push(self.scale(r));
+					packer.push(self.scale(g));
+					packer.push(self.scale(b));
+					packer.push(self.scale(a));
+				},
+				ColorType::Indexed => {
+					// both halves of the palette are still sorted
+					let index = match a {
+						255 => translucent.len() + opaque.binary_search(&[r, g, b, a]).unwrap_or(0),
+						_ => translucent.binary_search(&[r, g, b, a]).unwrap_or(0)
+					};
+					packer.push(index as u16);
+				}
+			}
+		}
+
+		packer.finish();
+	}
+
+	// whether every index in the packed rows points into the palette
+	pub fn valid_indices (&self, data: &[u8], stride: usize, row_len: usize, width: u32) -> bool {
+		let depth = self.bit_depth as usize;
+		let mask = ((1u16 << depth) - 1) as u8;
+
+		data.chunks(stride).all(|row| {
+			(0..width as usize).all(|x| {
+				let byte = row[..row_len][x * depth / 8];
+				let index = byte >> (8 - depth - x * depth % 8) & mask;
+				(index as usize) < self.palette.len()
+			})
+		})
+	}
+
+	// data of the PLTE chunk if the color type needs one
+	pub fn plte_data (&self) -> Option<Vec<u8>> {
+		(self.color_type == ColorType::Indexed).then(|| {
+			self.palette.iter().flat_map(|&[r, g, b, _]| [r, g, b]).collect()
+		})
+	}
+
+	// data of the tRNS chunk if the image needs one
+	pub fn trns_data (&self) -> Option<Vec<u8>> {
+		match self.color_type {
+			ColorType::Indexed => {
+				let translucent = self.palette.iter().rposition(|entry| entry[3] != 255)?;
+				Some(self.palette[..=translucent].iter().map(|entry| entry[3]).collect())
+			},
+			ColorType::Grayscale => self.transparent.map(|[v, _, _]| self.scale(v).to_be_bytes().to_vec()),
+			ColorType::Rgb => self.transparent.map(|rgb| rgb.iter().flat_map(|&c| self.scale(c).to_be_bytes()).collect()),
+			_ => None
+		}
+	}
+
+}
+
+
+
+// everything about an image's pixels needed to pick it's format
+struct Stats {
+	// every pixel has r == g == b
+	gray: bool,
+	// every pixel has a == 255
+	opaque: bool,
+	// smallest bit depth that can store every gray level exactly
+	gray_depth: u8,
+	// the rgb of fully transparent pixels, if every pixel is either
+	// fully opaque or fully transparent with this one rgb that no
+	// opaque pixel uses
+	transparent: Option<[u8; 3]>,
+	// sorted distinct pixels, `None` if there are more than 256
+	colors: Option<Vec<[u8; 4]>>
+}
+
+impl Stats {
+
+	fn collect (pixels: &[u8]) -> Self {
+		let mut stats = Self {
+			gray: true,
+			opaque: true,
+			gray_depth: 1,
+			transparent: None,
+			colors: Some(Vec::new())
+		};
+		let mut binary_alpha = true;
+
+		for pixel in pixels.chunks_exact(4) {
+			let [r, g, b, a] = [pixel[0], pixel[1], pixel[2], pixel[3]];
+
+			if r != g || g != b {
+				stats.gray = false;
+			} else {
+				stats.gray_depth = stats.gray_depth.max(sample_depth(r));
+			}
+
+			if a != 255 {
+				stats.opaque = false;
+				match stats.transparent {
+					_ if a != 0 => binary_alpha = false,
+					None => stats.transparent = Some([r, g, b]),
+					Some(rgb) if rgb != [r, g, b] => binary_alpha = false,
+					Some(_) => {}
+				}
+			}
+
+			if let Some(colors) = &mut stats.colors {
+				if let Err(i) = colors.binary_search(&[r, g, b, a]) {
+					if colors.len() == 256 {
+						stats.colors = None;
+					} else {
+						colors.insert(i, [r, g, b, a]);
+					}
+				}
+			}
+		}
+
+		// the transparent color can't also be used by an opaque pixel
+		if let Some(rgb) = stats.transparent {
+			let reused = pixels.chunks_exact(4).any(|pixel| pixel[3] == 255 && pixel[..3] == rgb);
+			if !binary_alpha || reused {
+				stats.transparent = None;
+			}
+		}
+
+		stats
+	}
+
+	fn palette_depth (&self) -> u8 {
+		match self.colors.as_ref().map_or(usize::MAX, |colors| colors.len()) {
+			0..=2 => 1,
+			3..=4 => 2,
+			5..=16 => 4,
+			_ => 8
+		}
+	}
+
+	// the format storing these pixels with `color_type` and `bit_depth`,
+	// `None` if it can't be done losslessly
+	fn format (&self, color_type: ColorType, bit_depth: u8) -> Option<Format> {
+		let transparent = match color_type {
+			ColorType::Grayscale | ColorType::Rgb if !self.opaque => Some(self.transparent?),
+			_ => None
+		};
+
+		let palette = match color_type {
+			ColorType::Grayscale if !self.gray || bit_depth < self.gray_depth => return None,
+			ColorType::GrayscaleAlpha if !self.gray => return None,
+			ColorType::Indexed => {
+				let colors = self.colors.as_ref()?;
+				if colors.len() > 1 << bit_depth {
+					return None;
+				}
+				let mut palette = colors.clone();
+				palette.sort_by_key(|entry| entry[3] == 255);
+				palette
+			},
+			_ => vec![]
+		};
+
+		Some(Format { color_type, bit_depth, palette, transparent })
+	}
+
+}
+
+// smallest bit depth that can store the 8 bit gray `sample` exactly
+fn sample_depth (sample: u8) -> u8 {
+	match sample {
+		_ if sample.is_multiple_of(255) => 1,
+		_ if sample.is_multiple_of(85) => 2,
+		_ if sample.is_multiple_of(17) => 4,
+		_ => 8
+	}
+}
+
+
+
+// packs samples of any bit depth into bytes, most significant bit first
+struct BitPacker<'a> {
+	bit_depth: u8,
+	out: &'a mut Vec<u8>,
+	current: u8,
+	filled: u8
+}
+
+impl<'a> BitPacker<'a> {
+
+	fn new (bit_depth: u8, out: &'a mut Vec<u8>) -> Self {
+		Self { bit_depth, out, current: 0, filled: 0 }
+	}
+
+	fn push (&mut self, sample: u16) {
+		match self.bit_depth {
+			16 => self.out.extend_from_slice(&sample.to_be_bytes()),
+			8 => self.out.push(sample as u8),
+			depth => {
+				self.current |= (sample as u8) << (8 - depth - self.filled);
+				self.filled += depth;
+				if self.filled == 8 {
+					self.out.push(self.current);
+					self.current = 0;
+					self.filled = 0;
+				}
+			}
+		}
+	}
+
+	// flush the last partially filled byte
+	fn finish (self) {
+		if self.filled > 0 {
+			self.out.push(self.current);
+		}
+	}
+
+}
+
+
+
+#[cfg(test)]
+mod tests {
+
+	use super::{ ColorType, Format };
+
+	#[cfg(not(feature = "std"))]
+	use alloc::vec::Vec;
+
+
+
+	fn candidates (pixels: &[u8]) -> Vec<(ColorType, u8)> {
+		Format::candidates(pixels).iter().map(|format| (format.color_type, format.bit_depth)).collect()
+	}
+
+	#[test]
+	fn test_candidates () {
+		assert_eq!(candidates(&[0, 0, 0, 255, 255, 255, 255, 255]), [(ColorType::Grayscale, 1), (ColorType::Indexed, 1)]);
+		assert_eq!(candidates(&[0, 0, 0, 255, 85, 85, 85, 255, 170, 170, 170, 255]), [(ColorType::Grayscale, 2), (ColorType::Indexed, 2)]);
+
+		let levels = (1..=15u8).flat_map(|v| [v * 17, v * 17, v * 17, 255]).chain([0, 0, 0, 0]).collect::<Vec<u8>>();
+		assert_eq!(candidates(&levels), [(ColorType::Grayscale, 4), (ColorType::Indexed, 4)]);
+		assert_eq!(candidates(&[235, 35, 35, 255]), [(ColorType::Rgb, 8), (ColorType::Indexed, 1)]);
+		assert_eq!(candidates(&[235, 35, 35, 127]), [(ColorType::Rgba, 8), (ColorType::Indexed, 1)]);
+		assert_eq!(candidates(&[235, 35, 35, 255, 1, 2, 3, 0]), [(ColorType::Rgb, 8), (ColorType::Indexed, 1)]);
+
+		let gray_alpha = (0..=255u8).flat_map(|v| [v, v, v, v]).chain([1, 1, 1, 2]).collect::<Vec<u8>>();
+		assert_eq!(candidates(&gray_alpha), [(ColorType::GrayscaleAlpha, 8)]);
+
+		let translucent = (0..=255u8).flat_map(|v| [v, 255 - v, 0, v]).chain([1, 2, 3, 4]).collect::<Vec<u8>>();
+		assert_eq!(candidates(&translucent), [(ColorType::Rgba, 8)]);
+	}
+
+	#[test]
+	fn test_requested_format () {
+		let pixels = [235, 35, 35, 255, 0, 0, 0, 0];
+		assert!(Format::requested(&pixels, ColorType::Rgb, 16).is_ok());
+		assert!(Format::requested(&pixels, ColorType::Rgba, 8).is_ok());
+		assert!(Format::requested(&pixels, ColorType::Grayscale, 8).is_err());
+		assert!(Format::requested(&pixels, ColorType::Rgb, 4).is_err());
+		assert!(Format::requested(&[1, 1, 1, 255], ColorType::Grayscale, 4).is_err());
+	}
+
+	#[test]
+	fn test_pack_row () {
+		let row = [0, 0, 0, 255, 255, 255, 255, 255, 0, 0, 0, 255];
+		let mut packed = Vec::new();
+		Format::requested(&row, ColorType::Grayscale, 1).unwrap().pack_row(&row, &mut packed);
+		assert_eq!(packed, [0b0100_0000]);
+
+		packed.clear();
+		Format::requested(&row, ColorType::Indexed, 2).unwrap().pack_row(&row, &mut packed);
+		assert_eq!(packed, [0b0001_0000]);
+
+		packed.clear();
+		Format::requested(&row, ColorType::GrayscaleAlpha, 16).unwrap().pack_row(&row, &mut packed);
+		assert_eq!(packed, [0, 0, 255, 255, 255, 255, 255, 255, 0, 0, 255, 255]);
+	}
+
+}
@@ -0,0 +1,308 @@
+// linear, radial and conic gradients between color stops, following
+// css images level 4 where it makes sense
+
+use core::f64::consts::PI;
+
+use crate::{ Color, Error };
+
+#[cfg(not(feature = "std"))]
+use alloc::vec::Vec;
+
+
+
+/// the color space gradients are interpolated in
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum Interpolation {
+	/// mix the srgb values directly, like css does by default, which
+	/// goes dark and muddy between very different colors
+	#[default]
+	Srgb,
+	/// mix physical amounts of light, brighter in the middle
+	LinearRgb,
+	/// mix in oklab, a perceptually uniform space that keeps the middle
+	/// of the gradient as bright and saturated as the ends
+	Oklab
+}
+
+// where the gradient's colors come from
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Shape {
+	Linear { angle: f64 },
+	Radial { center: (f64, f64), radius: f64 },
+	Conic { center: (f64, f64), angle: f64 }
+}
+
+
+
+/// a gradient image, for [`PngOptions::gradient`](crate::PngOptions::gradient)
+///
+/// stop positions go from 0 at the start of the gradient to 1 at the end,
+/// and are clamped to be at least the position of the stop before them
+///
+/// ```
+/// use hexpng::{ PngOptions, Gradient, Interpolation, Color };
+///
+/// let sunset = Gradient::linear(90.0)
+///     .stop(0.0, Color::rgb(235, 35, 35))
+///     .stop(1.0, Color::rgb(35, 35, 235))
+///     .interpolation(Interpolation::Oklab);
+/// let png = PngOptions::new().dimensions(256, 32).gradient(&sunset).unwrap();
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct Gradient {
+	shape: Shape,
+	stops: Vec<(f64, Color)>,
+	interpolation: Interpolation
+}
+
+impl Gradient {
+
+	/// a gradient along a line through the center of the image, at
+	/// `angle` degrees clockwise from pointing up, so 90 goes left to right
+	///
+	/// like css, the line is just long enough for the corners to get
+	/// the first and last colors
+	pub fn linear (angle: f64) -> Self {
+		Self::new(Shape::Linear { angle })
+	}
+
+	/// a circular gradient around (`x`, `y`), given as fractions of the
+	/// width and height, out to `radius` times the distance to the
+	/// farthest corner
+	pub fn radial (x: f64, y: f64, radius: f64) -> Self {
+		Self::new(Shape::Radial { center: (x, y), radius })
+	}
+
+	/// a gradient sweeping clockwise around (`x`, `y`), given as
+	/// fractions of the width and height, starting at `angle` degrees
+	/// clockwise from pointing up
+	pub fn conic (x: f64, y: f64, angle: f64) -> Self {
+		Self::new(Shape::Conic { center: (x, y), angle })
+	}
+
+	fn new (shape: Shape) -> Self {
+		Self { shape, stops: Vec::new(), interpolation: Interpolation::Srgb }
+	}
+
+	/// add a color stop at `position`
+	pub fn stop (mut self, position: f64, color: Color) -> Self {
+		let position = self.stops.last().map_or(position, |&(previous, _)| position.max(previous));
+		self.stops.push((position, color));
+		self
+	}
+
+	/// set the color space colors are mixed in, srgb by default
+	pub fn interpolation (mut self, interpolation: Interpolation) -> Self {
+		self.interpolation = interpolation;
+		self
+	}
+
+	// a function giving the color of pixel (x, y) of a `width` x `height` image
+	pub(crate) fn shader (&self, width: u32, height: u32) -> Result<impl Fn(u32, u32) -> [u8; 4] + '_, Error> {
+		if self.stops.is_empty() {
+			return Err(Error::UnsupportedOptions("a gradient needs at least one color stop"));
+		}
+
+		let stops = self.stops.iter().map(|&(position, color)| (position, to_space(color, self.interpolation))).collect::<Vec<_>>();
+		let (w, h) = (width as f64, height as f64);
+
+		Ok(move |x: u32, y: u32| {
+			// from the pixel's center
+			let (x, y) = (x as f64 + 0.5, y as f64 + 0.5);
+
+			let t = match self.shape {
+				Shape::Linear { angle } => {
+					let (sin, cos) = (libm::sin(angle.to_radians()), libm::cos(angle.to_radians()));
+					let length = libm::fabs(w * sin) + libm::fabs(h * cos);
+					((x - w / 2.0) * sin - (y - h / 2.0) * cos) / length + 0.5
+				},
+				Shape::Radial { center: (cx, cy), radius } => {
+					let (cx, cy) = (cx * w, cy * h);
+					let farthest = libm::hypot(cx.max(w - cx), cy.max(h - cy));
+					libm::hypot(x - cx, y - cy) / (farthest * radius)
+				},
+				Shape::Conic { center: (cx, cy), angle } => {
+					let turn = libm::atan2(x - cx * w, cy * h - y) / (2.0 * PI) - angle / 360.0;
+					turn - libm::floor(turn)
+				}
+			};
+
+			from_space(sample(&stops, t), self.interpolation)
+		})
+	}
+
+}
+
+
+
+// the premultiplied color at `t` between the stops
+fn sample (stops: &[(f64, [f64; 4])], t: f64) -> [f64; 4] {
+	let next = stops.iter().position(|&(position, _)| position > t);
+
+	match next {
+		Some(0) => stops[0].1,
+		None => stops[stops.len() - 1].1,
+		Some(i) => {
+			let ((start, from), (end, to)) = (stops[i - 1], stops[i]);
+			let u = (t - start) / (end - start);
+			core::array::from_fn(|channel| from[channel] + (to[channel] - from[channel]) * u)
+		}
+	}
+}
+
+// `color` in the interpolation space, premultiplied with alpha in the last channel
+fn to_space (color: Color, interpolation: Interpolation) -> [f64; 4] {
+	let srgb = [color.r, color.g, color.b].map(|c| c as f64 / 255.0);
+	let alpha = color.a as f64 / 255.0;
+
+	let [a, b, c] = match interpolation {
+		Interpolation::Srgb => srgb,
+		Interpolation::LinearRgb => srgb.map(to_linear),
+		Interpolation::Oklab => oklab(srgb.map(to_linear))
+	};
+
+	[a * alpha, b * alpha, c * alpha, alpha]
+}
+
+// reverse `to_space`, clamping colors outside srgb
+fn from_space (color: [f64; 4], interpolation: Interpolation) -> [u8; 4] {
+	let alpha = color[3];
+	if alpha <= 0.0 {
+		return [0; 4];
+	}
+
+	let unpremultiplied = [color[0] / alpha, color[1] / alpha, color[2] / alpha];
+	let srgb = match interpolation {
+		Interpolation::Srgb => unpremultiplied,
+		Interpolation::LinearRgb => unpremultiplied.map(from_linear),
+		Interpolation::Oklab => from_oklab(unpremultiplied).map(from_linear)
+	};
+
+	let [r, g, b] = srgb.map(to_u8);
+	[r, g, b, to_u8(alpha)]
+}
+
+pub(crate) fn to_u8 (value: f64) -> u8 {
+	// `as` saturates, and turns nan into 0
+	(value * 255.0 + 0.5) as u8
+}
+
+// srgb transfer function
+pub(crate) fn to_linear (c: f64) -> f64 {
+	if c <= 0.04045 {
+		c / 12.92
+	} else {
+		libm::pow((c + 0.055) / 1.055, 2.4)
+	}
+}
+
+pub(crate) fn from_linear (c: f64) -> f64 {
+	if c <= 0.0031308 {
+		c * 12.92
+	} else {
+		1.055 * libm::pow(c, 1.0 / 2.4) - 0.055
+	}
+}
+
+// linear srgb to oklab, https://bottosson.github.io/posts/oklab
+fn oklab ([r, g, b]: [f64; 3]) -> [f64; 3] {
+	let l = libm::cbrt(0.4122214708 * r + 0.5363325363 * g + 0.0514459929 * b);
+	let m = libm::cbrt(0.2119034982 * r + 0.6806995451 * g + 0.1073969566 * b);
+	let s = libm::cbrt(0.0883024619 * r + 0.2817188376 * g + 0.6299787005 * b);
+
+	[
+		0.2104542553 * l + 0.7936177850 * m - 0.0040720468 * s,
+		1.9779984951 * l - 2.4285922050 * m + 0.4505937099 * s,
+		0.0259040371 * l + 0.7827717662 * m - 0.8086757660 * s
+	]
+}
+
+fn from_oklab ([l, a, b]: [f64; 3]) -> [f64; 3] {
+	let l_ = l + 0.3963377774 * a + 0.2158037573 * b;
+	let m_ = l - 0.1055613458 * a - 0.0638541728 * b;
+	let s_ = l - 0.0894841775 * a - 1.2914855480 * b;
+	let (l, m, s) = (l_ * l_ * l_, m_ * m_ * m_, s_ * s_ * s_);
+
+	[
+		4.0767416621 * l - 3.3077115913 * m + 0.2309699292 * s,
+		-1.2684380046 * l + 2.6097574011 * m - 0.3413193965 * s,
+		-0.0041960863 * l - 0.7034186147 * m + 1.7076147010 * s
+	]
+}
+
+
+
+#[cfg(test)]
+mod tests {
+
+	use super::{ Gradient, Interpolation, oklab, from_oklab, to_linear, from_linear };
+	use crate::{ Color, Error };
+
+
+
+	const RED: Color = Color::rgb(255, 0, 0);
+	const BLUE: Color = Color::rgb(0, 0, 255);
+
+	#[test]
+	fn test_linear () {
+		let gradient = Gradient::linear(90.0).stop(0.0, RED).stop(1.0, BLUE);
+		let shader = gradient.shader(4, 1).unwrap();
+		assert_eq!([0, 1, 2, 3].map(|x| shader(x, 0)), [[223, 0, 32, 255], [159, 0, 96, 255], [96, 0, 159, 255], [32, 0, 223, 255]]);
+
+		// pointing up, the bottom row starts
+		let gradient = Gradient::linear(0.0).stop(0.0, RED).stop(1.0, BLUE);
+		let shader = gradient.shader(1, 2).unwrap();
+		assert_eq!([shader(0, 1), shader(0, 0)], [[191, 0, 64, 255], [64, 0, 191, 255]]);
+	}
+
+	#[test]
+	fn test_radial_and_conic () {
+		let gradient = Gradient::radial(0.5, 0.5, 1.0).stop(0.0, RED).stop(1.0, BLUE);
+		let shader = gradient.shader(100, 100).unwrap();
+		assert_eq!(shader(49, 49)[0], 252);
+		assert_eq!(shader(0, 0)[2], 252);
+
+		let gradient = Gradient::conic(0.5, 0.5, 0.0).stop(0.0, RED).stop(0.5, BLUE).stop(1.0, RED);
+		let shader = gradient.shader(100, 100).unwrap();
+		assert!(shader(50, 0)[0] >= 250);
+		assert_eq!(shader(50, 99)[2], 254);
+		assert!(shader(99, 50)[0].abs_diff(128) <= 2);
+	}
+
+	#[test]
+	fn test_stops () {
+		let gradient = Gradient::linear(90.0).stop(0.5, RED).stop(0.25, BLUE);
+		let shader = gradient.shader(4, 1).unwrap();
+		// the blue stop is moved to 0.5, so it's a hard edge
+		assert_eq!([0, 1, 2, 3].map(|x| shader(x, 0)), [[255, 0, 0, 255], [255, 0, 0, 255], [0, 0, 255, 255], [0, 0, 255, 255]]);
+
+		let solid = Gradient::conic(0.5, 0.5, 0.0).stop(0.3, RED);
+		assert_eq!(solid.shader(1, 1).unwrap()(0, 0), [255, 0, 0, 255]);
+
+		assert!(matches!(Gradient::linear(0.0).shader(1, 1), Err(Error::UnsupportedOptions(_))));
+	}
+
+	#[test]
+	fn test_interpolation () {
+		let (black, white) = (Color::rgb(0, 0, 0), Color::rgb(255, 255, 255));
+		let middle = |interpolation| Gradient::linear(90.0).stop(0.0, black).stop(1.0, white).interpolation(interpolation).shader(2, 1).unwrap()(0, 0);
+
+		// a quarter of the way from black to white
+		assert_eq!(middle(Interpolation::Srgb), [64, 64, 64, 255]);
+		assert_eq!(middle(Interpolation::LinearRgb), [137, 137, 137, 255]);
+		assert_eq!(middle(Interpolation::Oklab), [34, 34, 34, 255]);
+
+		// premultiplied, so fading to transparent doesn't go dark
+		let fade = Gradient::linear(90.0).stop(0.0, RED).stop(1.0, Color::new(0, 0, 0, 0));
+		assert_eq!(fade.shader(2, 1).unwrap()(0, 0), [255, 0, 0, 191]);
+
+		for c in [0.0, 0.001, 0.2, 0.5, 1.0] {
+			assert!((from_linear(to_linear(c)) - c).abs() < 1e-9);
+		}
+		let lab = oklab([1.0, 1.0, 1.0]);
+		assert!((lab[0] - 1.0).abs() < 1e-6 && lab[1].abs() < 1e-6 && lab[2].abs() < 1e-6);
+		let rgb = from_oklab(oklab([0.2, 0.5, 0.9]));
+		assert!(rgb.iter().zip([0.2, 0.5, 0.9]).all(|(a, b)| (a - b).abs() < 1e-6));
+	}
+
+}
@@ -0,0 +1,171 @@
+// deterministic avatars, a symmetric grid of cells picked by hashing some input
+
+use crate::{ Color, Error, css };
+
+
+
+const FNV_OFFSET_BASIS: u64 = 0xcbf29ce484222325;
+const FNV_PRIME: u64 = 0x100000001b3;
+
+
+
+/// a github style avatar for some input like a user id, for
+/// [`PngOptions::identicon`](crate::PngOptions::identicon)
+///
+/// the input is hashed with 64 bit fnv-1a, which seeds a splitmix64
+/// stream that picks the foreground color and which cells of the left
+/// half are filled, the right half mirroring them, so the same input
+/// always gives the same image
+///
+/// ```
+/// use hexpng::{ PngOptions, Identicon, Color };
+///
+/// let icon = Identicon::new("user-1234");
+/// let png = PngOptions::new().dimensions(240, 240).identicon(&icon).unwrap();
+///
+/// let icon = Identicon::new([0xde, 0xad, 0xbe, 0xef]).grid(7).background(Color::rgb(255, 255, 255));
+/// let png = PngOptions::new().dimensions(64, 64).identicon(&icon).unwrap();
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Identicon {
+	hash: u64,
+	grid: u32,
+	background: Color
+}
+
+impl Identicon {
+
+	/// an identicon of `input`, a 5x5 grid on a light gray background
+	pub fn new (input: impl AsRef<[u8]>) -> Self {
+		let hash = input.as_ref().iter().fold(FNV_OFFSET_BASIS, |hash, &byte| (hash ^ byte as u64).wrapping_mul(FNV_PRIME));
+		Self { hash, grid: 5, background: Color::rgb(240, 240, 240) }
+	}
+
+	/// use a `cells` x `cells` grid instead of 5x5
+	pub fn grid (mut self, cells: u32) -> Self {
+		self.grid = cells;
+		self
+	}
+
+	/// set the color behind the cells and of the margin around them
+	pub fn background (mut self, color: Color) -> Self {
+		self.background = color;
+		self
+	}
+
+	/// the color of the filled cells, a moderately saturated hue picked
+	/// by the hash
+	pub fn foreground (&self) -> Color {
+		let word = self.word(0);
+		let hue = (word % 360) as f32;
+		let saturation = 0.5 + ((word >> 16) % 21) as f32 / 100.0;
+		let lightness = 0.4 + ((word >> 32) % 21) as f32 / 100.0;
+
+		let [r, g, b] = css::hsl_to_rgb(hue, saturation, lightness).map(css::to_u8);
+		Color::rgb(r, g, b)
+	}
+
+	// the `index`th word of the hash's splitmix64 stream
+	fn word (&self, index: u64) -> u64 {
+		let mut z = self.hash.wrapping_add((index + 1).wrapping_mul(0x9e3779b97f4a7c15));
+		z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+		z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+		z ^ (z >> 31)
+	}
+
+	// whether cell (column, row) is filled, one bit of the stream per
+	// cell of the left half, including the middle column
+	fn filled (&self, column: u32, row: u32) -> bool {
+		let half = self.grid.div_ceil(2) as u64;
+		let column = column.min(self.grid - 1 - column) as u64;
+		let bit = row as u64 * half + column;
+		self.word(1 + bit / 64) >> (bit % 64) & 1 == 1
+	}
+
+	// a function giving the color of pixel (x, y) of a `width` x `height`
+	// image, the grid is centered with half a cell of margin around it
+	pub(crate) fn shader (&self, width: u32, height: u32) -> Result<impl Fn(u32, u32) -> [u8; 4] + '_, Error> {
+		if self.grid == 0 {
+			return Err(Error::UnsupportedOptions("an identicon grid needs at least one cell"));
+		}
+		let cell = width.min(height) as u64 / (self.grid as u64 + 1);
+		if cell == 0 {
+			return Err(Error::UnsupportedOptions("the png is too small for the identicon grid"));
+		}
+
+		let size = cell * self.grid as u64;
+		let (left, top) = ((width as u64 - size) / 2, (height as u64 - size) / 2);
+		let (foreground, background) = (self.foreground(), self.background);
+
+		Ok(move |x: u32, y: u32| {
+			let (x, y) = (x as u64, y as u64);
+			let inside = (left..left + size).contains(&x) && (top..top + size).contains(&y);
+			let Color { r, g, b, a } = if inside && self.filled(((x - left) / cell) as u32, ((y - top) / cell) as u32) {
+				foreground
+			} else {
+				background
+			};
+			[r, g, b, a]
+		})
+	}
+
+}
+
+
+
+#[cfg(test)]
+mod tests {
+
+	use super::Identicon;
+	use crate::{ Color, Error };
+
+	#[cfg(not(feature = "std"))]
+	use alloc::vec::Vec;
+
+
+
+	#[test]
+	fn test_hash () {
+		// fnv-1a test vectors
+		assert_eq!(Identicon::new("").hash, 0xcbf29ce484222325);
+		assert_eq!(Identicon::new("a").hash, 0xaf63dc4c8601ec8c);
+		assert_eq!(Identicon::new("foobar").hash, 0x85944171f73967e8);
+
+		assert_eq!(Identicon::new("user-1234"), Identicon::new(b"user-1234"));
+		assert_ne!(Identicon::new("user-1234").foreground(), Identicon::new("user-1235").foreground());
+	}
+
+	#[test]
+	fn test_symmetry () {
+		for grid in [1, 4, 5, 12] {
+			let icon = Identicon::new("symmetric").grid(grid);
+			for row in 0..grid {
+				let cells = (0..grid).map(|column| icon.filled(column, row)).collect::<Vec<_>>();
+				assert!(cells.iter().eq(cells.iter().rev()), "row {row} of a {grid}x{grid} grid isn't symmetric");
+			}
+		}
+	}
+
+	#[test]
+	fn test_shader () {
+		let icon = Identicon::new("user-1234").background(Color::rgb(0, 0, 0));
+		let foreground = icon.foreground();
+		let shader = icon.shader(13, 12).unwrap();
+
+		// 2 pixel cells with a pixel of margin, plus the odd one out on the right
+		let margin = [0, 0, 0, 255];
+		assert_eq!([shader(0, 0), shader(11, 5), shader(12, 5), shader(5, 0), shader(5, 11)], [margin; 5]);
+		for (column, row) in [(0, 0), (2, 1), (4, 4)] {
+			let expected = match icon.filled(column, row) {
+				true => [foreground.r, foreground.g, foreground.b, 255],
+				false => margin
+			};
+			assert_eq!(shader(1 + column * 2, 1 + row * 2), expected);
+			assert_eq!(shader(2 + column * 2, 2 + row * 2), expected);
+		}
+
+		assert!(matches!(icon.shader(5, 100), Err(Error::UnsupportedOptions(_))));
+		assert!(matches!(icon.grid(0).shader(100, 100), Err(Error::UnsupportedOptions(_))));
+	}
+
+}
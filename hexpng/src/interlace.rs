@@ -0,0 +1,140 @@
+// adam7 interlacing, splitting an image into 7 passes of sub images
+// so decoders can show a coarse preview long before the whole png is in
+
+#[cfg(not(feature = "std"))]
+use alloc::{ vec, vec::Vec };
+
+
+
+// x start, y start, x step and y step of each pass
+const PASSES: [(usize, usize, usize, usize); 7] = [
+	(0, 0, 8, 8),
+	(4, 0, 8, 8),
+	(0, 4, 4, 8),
+	(2, 0, 4, 4),
+	(0, 2, 2, 4),
+	(1, 0, 2, 2),
+	(0, 1, 1, 2)
+];
+
+// width and height in pixels of every pass, some may be empty
+fn pass_dimensions (width: u32, height: u32) -> impl Iterator<Item = ((usize, usize, usize, usize), usize, usize)> {
+	PASSES.into_iter().map(move |pass @ (x0, y0, dx, dy)| {
+		let w = (width as usize + dx - 1 - x0) / dx;
+		let h = (height as usize + dy - 1 - y0) / dy;
+		(pass, w, h)
+	})
+}
+
+// row length in bytes (without the filter byte) and number of rows of
+// every non empty pass, in the order they're stored
+pub(crate) fn passes (width: u32, height: u32, bits_per_pixel: usize) -> impl Iterator<Item = (usize, usize)> {
+	pass_dimensions(width, height)
+		.filter(|&(_, w, h)| w > 0 && h > 0)
+		.map(move |(_, w, h)| ((w * bits_per_pixel).div_ceil(8), h))
+}
+
+// reorder non interlaced scanlines (each a filter byte + the packed
+// pixels) into the scanlines of the 7 passes, with 0 filter bytes
+pub(crate) fn interlace (data: &[u8], width: u32, height: u32, bits_per_pixel: usize) -> Vec<u8> {
+	let row_len = (width as usize * bits_per_pixel).div_ceil(8);
+	let mut interlaced = Vec::new();
+
+	for ((x0, y0, dx, dy), w, h) in pass_dimensions(width, height).filter(|&(_, w, h)| w > 0 && h > 0) {
+		let pass_row_len = (w * bits_per_pixel).div_ceil(8);
+
+		for j in 0..h {
+			let source = &data[(y0 + j * dy) * (row_len + 1) + 1..][..row_len];
+			let mut row = vec![0u8; pass_row_len + 1];
+			for i in 0..w {
+				copy_pixel(source, x0 + i * dx, &mut row[1..], i, bits_per_pixel);
+			}
+			interlaced.extend_from_slice(&row);
+		}
+	}
+
+	interlaced
+}
+
+// reverse `interlace`, the passes must already be unfiltered
+pub(crate) fn deinterlace (data: &[u8], width: u32, height: u32, bits_per_pixel: usize) -> Vec<u8> {
+	let row_len = (width as usize * bits_per_pixel).div_ceil(8);
+	let mut image = vec![0u8; (row_len + 1) * height as usize];
+	let mut rest = data;
+
+	for ((x0, y0, dx, dy), w, h) in pass_dimensions(width, height).filter(|&(_, w, h)| w > 0 && h > 0) {
+		let pass_row_len = (w * bits_per_pixel).div_ceil(8);
+
+		for j in 0..h {
+			let row = &mut image[(y0 + j * dy) * (row_len + 1) + 1..][..row_len];
+			for i in 0..w {
+				copy_pixel(&rest[1..], i, row, x0 + i * dx, bits_per_pixel);
+			}
+			rest = &rest[pass_row_len + 1..];
+		}
+	}
+
+	image
+}
+
+// copy pixel `sx` of `source` to pixel `dx` of `destination`, which has to
+// be zeroed for bit depths below 8
+fn copy_pixel (source: &[u8], sx: usize, destination: &mut [u8], dx: usize, bits_per_pixel: usize) {
+	if bits_per_pixel >= 8 {
+		let bytes = bits_per_pixel / 8;
+		destination[dx * bytes..][..bytes].copy_from_slice(&source[sx * bytes..][..bytes]);
+	} else {
+		let mask = (1u8 << bits_per_pixel) - 1;
+		let (source_bit, destination_bit) = (sx * bits_per_pixel, dx * bits_per_pixel);
+		let pixel = source[source_bit / 8] >> (8 - bits_per_pixel - source_bit % 8) & mask;
+		destination[destination_bit / 8] |= pixel << (8 - bits_per_pixel - destination_bit % 8);
+	}
+}
+
+
+
+#[cfg(test)]
+mod tests {
+
+	use super::{ interlace, deinterlace, passes };
+
+	#[cfg(not(feature = "std"))]
+	use alloc::vec::Vec;
+
+
+
+	#[test]
+	fn test_round_trip () {
+		for (width, height) in [(1, 1), (3, 5), (8, 8), (9, 9), (17, 3), (2, 31)] {
+			for bits_per_pixel in [1, 2, 4, 8, 16, 24, 32, 48, 64] {
+				let row_len = (width as usize * bits_per_pixel).div_ceil(8);
+
+				// keep the padding bits at the end of each row zeroed
+				let padding = row_len * 8 - width as usize * bits_per_pixel;
+				let data = (0..height as usize).flat_map(|y| {
+					let mut row = (0..row_len).map(|x| (x * 31 + y * 17 + 1) as u8).collect::<Vec<u8>>();
+					if let Some(last) = row.last_mut() {
+						*last &= (0xffu16 << padding) as u8;
+					}
+					[0].into_iter().chain(row)
+				}).collect::<Vec<u8>>();
+
+				let interlaced = interlace(&data, width, height, bits_per_pixel);
+				let expected_len = passes(width, height, bits_per_pixel).map(|(row_len, rows)| (row_len + 1) * rows).sum::<usize>();
+				assert_eq!(interlaced.len(), expected_len);
+				assert_eq!(deinterlace(&interlaced, width, height, bits_per_pixel), data, "{}x{} {} bits didn't round trip", width, height, bits_per_pixel);
+			}
+		}
+	}
+
+	#[test]
+	fn test_pass_order () {
+		// 8x8 image where every pixel's value is it's index
+		let data = (0..8u8).flat_map(|y| [0].into_iter().chain((0..8).map(move |x| y * 8 + x))).collect::<Vec<u8>>();
+		let interlaced = interlace(&data, 8, 8, 8);
+
+		assert_eq!(passes(8, 8, 8).collect::<Vec<_>>(), [(1, 1), (1, 1), (2, 1), (2, 2), (4, 2), (4, 4), (8, 4)]);
+		assert_eq!(interlaced[..8], [0, 0, 0, 4, 0, 32, 36, 0]);
+	}
+
+}
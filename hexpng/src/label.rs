@@ -0,0 +1,246 @@
+// text drawn onto images with a small built in bitmap font
+
+use crate::{ Color, Error, gradient, pattern };
+
+#[cfg(not(feature = "std"))]
+use alloc::{ format, vec::Vec };
+
+
+
+// glyphs for the printable ascii characters, space to tilde
+static FONT: [[u8; 7]; 95] = include!("font_table.rs");
+
+const GLYPH_WIDTH: u32 = 5;
+const GLYPH_HEIGHT: u32 = 7;
+// from the left edge of a glyph to the next, leaving a column between them
+const ADVANCE: u32 = GLYPH_WIDTH + 1;
+
+const BLACK: Color = Color::rgb(0, 0, 0);
+const WHITE: Color = Color::rgb(255, 255, 255);
+
+
+
+/// a line of text drawn in the middle of an image, for [`PngOptions::label`](crate::PngOptions::label)
+///
+/// unless a color is set the text is black or white, whichever
+/// contrasts more with what's under it
+///
+/// ```
+/// use hexpng::{ PngOptions, Label, Color };
+///
+/// let color = Color::rgb(235, 35, 35);
+/// // #EB2323 in black, which contrasts more with it than white
+/// let png = PngOptions::new().dimensions(128, 64).label(Label::hex(color)).generate(235, 35, 35, 255).unwrap();
+///
+/// let label = Label::new("brand red").unwrap().scale(2).color(Color::rgb(255, 255, 0));
+/// let png = PngOptions::new().dimensions(128, 64).label(label).generate(235, 35, 35, 255).unwrap();
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Label {
+	text: Vec<u8>,
+	scale: Option<u32>,
+	color: Option<Color>
+}
+
+impl Label {
+
+	/// a label of printable ascii `text`, as large as fits comfortably
+	pub fn new (text: &str) -> Result<Self, Error> {
+		if !text.bytes().all(|b| (b' '..=b'~').contains(&b)) {
+			return Err(Error::UnsupportedOptions("labels can only contain printable ascii"));
+		}
+		Ok(Self { text: text.as_bytes().to_vec(), scale: None, color: None })
+	}
+
+	/// a label of the color's hexcode, like `#EB2323`
+	pub fn hex (color: Color) -> Self {
+		Self { text: format!("{color}").into_bytes(), scale: None, color: None }
+	}
+
+	/// draw every pixel of the 5x7 font as `scale` x `scale` pixels
+	/// instead of picking the largest scale that fits
+	pub fn scale (mut self, scale: u32) -> Self {
+		self.scale = Some(scale);
+		self
+	}
+
+	/// draw the text in `color` instead of black or white
+	pub fn color (mut self, color: Color) -> Self {
+		self.color = Some(color);
+		self
+	}
+
+	// the text color over a background of relative `luminance`
+	pub(crate) fn color_over (&self, luminance: f64) -> Color {
+		self.color.unwrap_or_else(|| contrast(luminance))
+	}
+
+	// where the label goes when centered in a `width` x `height` region,
+	// by default it's scaled to at most 3/4 of the width and half the height
+	pub(crate) fn place (&self, width: u32, height: u32) -> Placement<'_> {
+		let text_width = (self.text.len() as u64 * ADVANCE as u64).saturating_sub(1);
+		let scale = self.scale.unwrap_or_else(|| {
+			let fit = (width as u64 * 3 / 4).checked_div(text_width).unwrap_or(u64::MAX).min(height as u64 / 2 / GLYPH_HEIGHT as u64);
+			fit.clamp(1, u32::MAX as u64) as u32
+		}).max(1);
+
+		Placement {
+			text: &self.text,
+			scale,
+			x: (width as i64 - (text_width * scale as u64) as i64) / 2,
+			y: (height as i64 - (GLYPH_HEIGHT as u64 * scale as u64) as i64) / 2
+		}
+	}
+
+	// draw the label over `width` x `height` tightly packed rgba8 pixels,
+	// picking the text color from the average luminance under the label
+	pub(crate) fn draw (&self, pixels: &mut [u8], width: u32, height: u32) {
+		let placement = self.place(width, height);
+		let covered = || (0..height).flat_map(move |y| (0..width).map(move |x| (x, y)))
+			.filter(|&(x, y)| placement.covers(x, y))
+			.map(|(x, y)| (y as usize * width as usize + x as usize) * 4);
+
+		let (total, count) = covered().fold((0.0, 0u64), |(total, count), i| {
+			let [r, g, b, a] = [pixels[i], pixels[i + 1], pixels[i + 2], pixels[i + 3]];
+			(total + luminance(Color::new(r, g, b, a)), count + 1)
+		});
+		if count == 0 {
+			return;
+		}
+		let color = self.color_over(total / count as f64);
+
+		for i in covered() {
+			let [r, g, b, a] = [pixels[i], pixels[i + 1], pixels[i + 2], pixels[i + 3]];
+			let Color { r, g, b, a } = pattern::over(color, Color::new(r, g, b, a));
+			pixels[i..i + 4].copy_from_slice(&[r, g, b, a]);
+		}
+	}
+
+}
+
+
+
+// a label's scale and top left corner inside some region
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Placement<'a> {
+	text: &'a [u8],
+	scale: u32,
+	x: i64,
+	y: i64
+}
+
+impl Placement<'_> {
+
+	// whether the text covers pixel (x, y) of the region
+	pub(crate) fn covers (&self, x: u32, y: u32) -> bool {
+		let (dx, dy) = (x as i64 - self.x, y as i64 - self.y);
+		if dx < 0 || dy < 0 {
+			return false;
+		}
+
+		let (column, row) = ((dx / self.scale as i64) as u64, (dy / self.scale as i64) as u64);
+		let (index, column) = (column / ADVANCE as u64, (column % ADVANCE as u64) as u32);
+		match self.text.get(index as usize) {
+			Some(&c) if row < GLYPH_HEIGHT as u64 && column < GLYPH_WIDTH => FONT[(c - b' ') as usize][row as usize] >> (GLYPH_WIDTH - 1 - column) & 1 == 1,
+			_ => false
+		}
+	}
+
+}
+
+
+
+// wcag relative luminance of a color, translucent colors as they'd
+// look on a white page
+pub(crate) fn luminance (color: Color) -> f64 {
+	let Color { r, g, b, .. } = pattern::over(color, WHITE);
+	let linear = |c: u8| gradient::to_linear(c as f64 / 255.0);
+	0.2126 * linear(r) + 0.7152 * linear(g) + 0.0722 * linear(b)
+}
+
+// black or white, whichever has the higher wcag contrast ratio against
+// a background of relative `luminance`
+fn contrast (luminance: f64) -> Color {
+	if (luminance + 0.05) / 0.05 > 1.05 / (luminance + 0.05) { BLACK } else { WHITE }
+}
+
+
+
+#[cfg(test)]
+mod tests {
+
+	use super::{ Label, luminance, contrast, BLACK, WHITE };
+	use crate::{ Color, Error };
+
+	#[cfg(not(feature = "std"))]
+	use alloc::vec::Vec;
+
+
+
+	// the label as rows of `#` and `.`
+	fn ascii_art (label: &Label, width: u32, height: u32) -> Vec<Vec<u8>> {
+		let placement = label.place(width, height);
+		(0..height).map(|y| (0..width).map(|x| if placement.covers(x, y) { b'#' } else { b'.' }).collect()).collect()
+	}
+
+	#[test]
+	fn test_glyphs () {
+		let label = Label::new("H1").unwrap();
+		assert_eq!(ascii_art(&label, 13, 9), [
+			b".............",
+			b".#..#....#...",
+			b".#..#...##...",
+			b".####....#...",
+			b".#..#....#...",
+			b".#..#....#...",
+			b".#..#...###..",
+			b".............",
+			b"............."
+		]);
+
+		assert!(matches!(Label::new("caf\u{e9}"), Err(Error::UnsupportedOptions(_))));
+		assert!(matches!(Label::new("tab\t"), Err(Error::UnsupportedOptions(_))));
+	}
+
+	#[test]
+	fn test_scale () {
+		let label = Label::hex(Color::rgb(235, 35, 35));
+		assert_eq!(label.text, b"#EB2323");
+
+		// 41 pixels wide and 7 high at scale 1
+		assert_eq!(label.place(100, 100).scale, 1);
+		assert_eq!(label.place(110, 100).scale, 2);
+		assert_eq!(label.place(1000, 28).scale, 2);
+		assert_eq!(label.place(10, 10).scale, 1);
+		assert_eq!(label.clone().scale(3).place(10, 10).scale, 3);
+
+		let placement = label.place(110, 100);
+		assert_eq!((placement.x, placement.y), (14, 43));
+		assert!(placement.covers(14 + 2, 43 + 2));
+		assert!(!placement.covers(14, 43));
+	}
+
+	#[test]
+	fn test_contrast () {
+		assert_eq!(contrast(luminance(Color::rgb(235, 35, 35))), BLACK);
+		assert_eq!(contrast(luminance(Color::rgb(200, 35, 35))), WHITE);
+		assert_eq!(contrast(luminance(Color::rgb(35, 35, 235))), WHITE);
+		assert_eq!(contrast(luminance(Color::rgb(0, 0, 0))), WHITE);
+		// on a white page
+		assert_eq!(contrast(luminance(Color::new(0, 0, 0, 0))), BLACK);
+
+		assert_eq!(Label::new("a").unwrap().color_over(0.0), WHITE);
+		assert_eq!(Label::new("a").unwrap().color(BLACK).color_over(0.0), BLACK);
+	}
+
+	#[test]
+	fn test_draw () {
+		let mut pixels = [35, 35, 235, 255].repeat(13 * 9);
+		Label::new("H1").unwrap().draw(&mut pixels, 13, 9);
+
+		let pixel = |x: usize, y: usize| &pixels[(y * 13 + x) * 4..][..4];
+		assert_eq!(pixel(1, 1), [255, 255, 255, 255]);
+		assert_eq!(pixel(0, 0), [35, 35, 235, 255]);
+	}
+
+}
@@ -0,0 +1,198 @@
+// physical pixel dimensions and last modification time, stored in pHYs
+// and tIME chunks
+
+use crate::{ Error, png::Chunk };
+
+
+
+const METERS_PER_INCH: f64 = 0.0254;
+
+
+
+/// unit of a [`Resolution`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum Unit {
+	/// no unit, the resolution only gives the pixel aspect ratio
+	#[default]
+	Unknown = 0,
+	/// pixels per meter
+	Meter = 1
+}
+
+
+
+/// pixels per unit on the x and y axes, stored in a pHYs chunk
+///
+/// ```
+/// use hexpng::{ PngOptions, Resolution };
+///
+/// let print = PngOptions::new().dimensions(300, 300).resolution(Resolution::dpi(300.0));
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Resolution {
+	/// pixels per unit horizontally
+	pub x: u32,
+	/// pixels per unit vertically
+	pub y: u32,
+	/// unit of `x` and `y`
+	pub unit: Unit
+}
+
+impl Resolution {
+
+	/// `x` and `y` pixels per meter
+	pub const fn per_meter (x: u32, y: u32) -> Self {
+		Self { x, y, unit: Unit::Meter }
+	}
+
+	/// just the pixel aspect ratio `x`:`y`, without a physical size
+	pub const fn aspect_ratio (x: u32, y: u32) -> Self {
+		Self { x, y, unit: Unit::Unknown }
+	}
+
+	/// the same number of dots (pixels) per inch on both axes, rounded
+	/// to whole pixels per meter as that's all png can store
+	pub fn dpi (dpi: f64) -> Self {
+		Self::dpi_xy(dpi, dpi)
+	}
+
+	/// different dots (pixels) per inch on the x and y axes
+	pub fn dpi_xy (x: f64, y: f64) -> Self {
+		// `as` saturates, and turns nan into 0
+		Self::per_meter((x / METERS_PER_INCH + 0.5) as u32, (y / METERS_PER_INCH + 0.5) as u32)
+	}
+
+	/// the x and y dots per inch, if the unit is known
+	pub fn to_dpi (&self) -> Option<(f64, f64)> {
+		match self.unit {
+			Unit::Meter => Some((self.x as f64 * METERS_PER_INCH, self.y as f64 * METERS_PER_INCH)),
+			Unit::Unknown => None
+		}
+	}
+
+	pub(crate) fn chunk (&self) -> Result<Chunk, Error> {
+		// the spec limits every 4 byte integer to 2^31-1
+		if self.x == 0 || self.y == 0 || self.x > i32::MAX as u32 || self.y > i32::MAX as u32 {
+			return Err(Error::InvalidChunk("pixels per unit have to be 1 to 2^31-1"));
+		}
+
+		Ok((b"pHYs", [self.x.to_be_bytes(), self.y.to_be_bytes()].concat().into_iter().chain([self.unit as u8]).collect()))
+	}
+
+}
+
+
+
+/// a utc date and time, stored in a tIME chunk as the last time the
+/// image was modified
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Timestamp {
+	year: u16,
+	month: u8,
+	day: u8,
+	hour: u8,
+	minute: u8,
+	second: u8
+}
+
+impl Timestamp {
+
+	/// fails with [`Error::InvalidChunk`] if it isn't a valid date and
+	/// time, `second` can be 60 for leap seconds
+	pub fn new (year: u16, month: u8, day: u8, hour: u8, minute: u8, second: u8) -> Result<Self, Error> {
+		if !(1..=12).contains(&month) || day == 0 || day > days_in_month(year, month) {
+			return Err(Error::InvalidChunk("not a valid date"));
+		}
+		if hour > 23 || minute > 59 || second > 60 {
+			return Err(Error::InvalidChunk("not a valid time"));
+		}
+
+		Ok(Self { year, month, day, hour, minute, second })
+	}
+
+	/// the time `seconds` after the unix epoch, 1970-01-01 00:00:00 utc
+	pub fn from_unix (seconds: u64) -> Result<Self, Error> {
+		let (days, time) = (seconds / 86400, seconds % 86400);
+
+		// days to a civil date, http://howardhinnant.github.io/date_algorithms.html#civil_from_days
+		let z = days + 719_468;
+		let (era, day_of_era) = (z / 146_097, z % 146_097);
+		let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146_096) / 365;
+		let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+		let shifted_month = (5 * day_of_year + 2) / 153;
+		let day = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+		let month = if shifted_month < 10 { shifted_month + 3 } else { shifted_month - 9 };
+		let year = year_of_era + era * 400 + (month <= 2) as u64;
+
+		let year = u16::try_from(year).map_err(|_| Error::InvalidChunk("year doesn't fit in 16 bits"))?;
+		Self::new(year, month as u8, day as u8, (time / 3600) as u8, (time / 60 % 60) as u8, (time % 60) as u8)
+	}
+
+	/// the current time according to the system clock
+	#[cfg(feature = "std")]
+	pub fn now () -> Result<Self, Error> {
+		let since_epoch = std::time::SystemTime::now()
+			.duration_since(std::time::UNIX_EPOCH)
+			.map_err(|_| Error::InvalidChunk("system clock is before the unix epoch"))?;
+		Self::from_unix(since_epoch.as_secs())
+	}
+
+	pub(crate) fn chunk (&self) -> Chunk {
+		let mut data = self.year.to_be_bytes().to_vec();
+		data.extend([self.month, self.day, self.hour, self.minute, self.second]);
+		(b"tIME", data)
+	}
+
+}
+
+fn days_in_month (year: u16, month: u8) -> u8 {
+	match month {
+		2 if year.is_multiple_of(4) && (!year.is_multiple_of(100) || year.is_multiple_of(400)) => 29,
+		2 => 28,
+		4 | 6 | 9 | 11 => 30,
+		_ => 31
+	}
+}
+
+
+
+#[cfg(test)]
+mod tests {
+
+	use super::{ Resolution, Timestamp, Unit };
+	use crate::Error;
+
+
+
+	#[test]
+	fn test_resolution () {
+		let resolution = Resolution::dpi(300.0);
+		assert_eq!(resolution, Resolution::per_meter(11811, 11811));
+		let (x, y) = resolution.to_dpi().unwrap();
+		assert!((x - 300.0).abs() < 0.01 && (y - 300.0).abs() < 0.01);
+		assert_eq!(Resolution::dpi_xy(72.0, 144.0), Resolution::per_meter(2835, 5669));
+		assert_eq!(Resolution::aspect_ratio(2, 1).to_dpi(), None);
+
+		let (chunk_type, data) = Resolution::dpi(300.0).chunk().unwrap();
+		assert_eq!(chunk_type, b"pHYs");
+		assert_eq!(data, [0, 0, 0x2e, 0x23, 0, 0, 0x2e, 0x23, Unit::Meter as u8]);
+
+		assert!(matches!(Resolution::dpi(0.0).chunk(), Err(Error::InvalidChunk(_))));
+		assert!(matches!(Resolution::aspect_ratio(u32::MAX, 1).chunk(), Err(Error::InvalidChunk(_))));
+	}
+
+	#[test]
+	fn test_timestamp () {
+		assert_eq!(Timestamp::from_unix(0), Timestamp::new(1970, 1, 1, 0, 0, 0));
+		assert_eq!(Timestamp::from_unix(951_782_400), Timestamp::new(2000, 2, 29, 0, 0, 0));
+		assert_eq!(Timestamp::from_unix(1_700_000_000), Timestamp::new(2023, 11, 14, 22, 13, 20));
+		assert!(Timestamp::from_unix(u64::MAX).is_err());
+
+		assert_eq!(Timestamp::new(2024, 2, 29, 23, 59, 60).unwrap().chunk(), (b"tIME", [0x07, 0xe8, 2, 29, 23, 59, 60].to_vec()));
+		for (year, month, day) in [(2023, 2, 29), (1900, 2, 29), (2024, 4, 31), (2024, 0, 1), (2024, 13, 1), (2024, 1, 0)] {
+			assert!(matches!(Timestamp::new(year, month, day, 0, 0, 0), Err(Error::InvalidChunk(_))), "{}-{}-{} is a valid date", year, month, day);
+		}
+		assert!(matches!(Timestamp::new(2024, 1, 1, 24, 0, 0), Err(Error::InvalidChunk(_))));
+	}
+
+}
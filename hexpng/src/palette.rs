@@ -0,0 +1,244 @@
+// a brand palette as one image, swatches laid out in a strip or grid
+
+use crate::{ Color, Error, Label, label };
+
+#[cfg(not(feature = "std"))]
+use alloc::vec::Vec;
+
+
+
+/// how the swatches of a [`Palette`] are arranged
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum Layout {
+	/// one row, left to right
+	#[default]
+	Horizontal,
+	/// one column, top to bottom
+	Vertical,
+	/// rows of `columns` swatches, left to right then top to bottom
+	Grid { columns: u32 }
+}
+
+
+
+/// swatches of a list of colors, for [`PngOptions::palette`](crate::PngOptions::palette)
+///
+/// ```
+/// use hexpng::{ PngOptions, Palette, Layout, Color };
+///
+/// let colors = ["#eb2323", "#23eb23", "#2323eb", "#ebeb23", "#eb23eb"].map(|hex| hex.parse::<Color>().unwrap());
+/// let brand = Palette::new(&colors)
+///     .layout(Layout::Grid { columns: 3 })
+///     .swatch_size(32, 32)
+///     .gutter(4, Color::rgb(255, 255, 255))
+///     .border(2, Color::rgb(0, 0, 0));
+/// let png = PngOptions::new().palette(&brand).unwrap();
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Palette {
+	colors: Vec<Color>,
+	layout: Layout,
+	swatch: (u32, u32),
+	gutter: (u32, Color),
+	border: (u32, Color),
+	labels: bool
+}
+
+impl Palette {
+
+	/// a horizontal strip of 64x64 pixel swatches without gutters or a border
+	pub fn new (colors: &[Color]) -> Self {
+		Self {
+			colors: colors.to_vec(),
+			layout: Layout::Horizontal,
+			swatch: (64, 64),
+			gutter: (0, Color::default()),
+			border: (0, Color::default()),
+			labels: false
+		}
+	}
+
+	/// set how the swatches are arranged
+	pub fn layout (mut self, layout: Layout) -> Self {
+		self.layout = layout;
+		self
+	}
+
+	/// set the size of each swatch in pixels
+	pub fn swatch_size (mut self, width: u32, height: u32) -> Self {
+		self.swatch = (width, height);
+		self
+	}
+
+	/// put `width` pixels of `color` between swatches, the color also
+	/// fills the empty cells at the end of a grid, transparent by default
+	pub fn gutter (mut self, width: u32, color: Color) -> Self {
+		self.gutter = (width, color);
+		self
+	}
+
+	/// surround the swatches with `width` pixels of `color`
+	pub fn border (mut self, width: u32, color: Color) -> Self {
+		self.border = (width, color);
+		self
+	}
+
+	/// print each swatch's hexcode on it, in black or white whichever
+	/// contrasts more with the swatch
+	pub fn labels (mut self, labels: bool) -> Self {
+		self.labels = labels;
+		self
+	}
+
+	// each swatch's label and its text color
+	fn swatch_labels (&self) -> Vec<(Label, Color)> {
+		match self.labels {
+			true => self.colors.iter().map(|&color| (Label::hex(color), Label::hex(color).color_over(label::luminance(color)))).collect(),
+			false => Vec::new()
+		}
+	}
+
+	// columns and rows of swatches
+	fn grid (&self) -> Result<(u32, u32), Error> {
+		let count = u32::try_from(self.colors.len()).map_err(|_| Error::ImageTooLarge)?;
+		if count == 0 {
+			return Err(Error::UnsupportedOptions("a palette needs at least one color"));
+		}
+
+		let columns = match self.layout {
+			Layout::Horizontal => count,
+			Layout::Vertical => 1,
+			Layout::Grid { columns: 0 } => return Err(Error::UnsupportedOptions("a palette grid needs at least one column")),
+			Layout::Grid { columns } => columns.min(count)
+		};
+		Ok((columns, count.div_ceil(columns)))
+	}
+
+	// width and height of the whole image
+	pub(crate) fn dimensions (&self) -> Result<(u32, u32), Error> {
+		let (columns, rows) = self.grid()?;
+		let length = |cells: u32, swatch: u32| {
+			(cells as u64 * swatch as u64 + (cells as u64 - 1) * self.gutter.0 as u64 + 2 * self.border.0 as u64)
+				.try_into()
+				.map_err(|_| Error::ImageTooLarge)
+		};
+		Ok((length(columns, self.swatch.0)?, length(rows, self.swatch.1)?))
+	}
+
+	// every color that appears in the image, as rgba8 pixels
+	pub(crate) fn pixels (&self) -> Result<Vec<u8>, Error> {
+		let (columns, rows) = self.grid()?;
+		let gutter = self.gutter.0 > 0 || (columns * rows) as usize > self.colors.len();
+		let border = self.border.0 > 0;
+
+		let labels = self.swatch_labels();
+
+		Ok(self.colors.iter()
+			.chain(gutter.then_some(&self.gutter.1))
+			.chain(border.then_some(&self.border.1))
+			.chain(labels.iter().map(|(_, color)| color))
+			.flat_map(|c| [c.r, c.g, c.b, c.a])
+			.collect())
+	}
+
+	// a function giving the color of pixel (x, y)
+	pub(crate) fn shader (&self) -> Result<impl Fn(u32, u32) -> [u8; 4] + '_, Error> {
+		let (columns, _) = self.grid()?;
+		let (width, height) = self.dimensions()?;
+		let (gutter, border) = (self.gutter.0, self.border.0);
+		let labels = self.swatch_labels();
+
+		// which swatch `position` is in along an axis and how far into
+		// it, `None` in a gutter
+		let cell = move |position: u32, swatch: u32| {
+			let stride = swatch as u64 + gutter as u64;
+			let offset = (position - border) as u64;
+			(offset % stride < swatch as u64).then_some((offset / stride, (offset % stride) as u32))
+		};
+
+		Ok(move |x: u32, y: u32| {
+			let color = if x < border || y < border || x >= width - border || y >= height - border {
+				self.border.1
+			} else {
+				cell(x, self.swatch.0)
+					.zip(cell(y, self.swatch.1))
+					.and_then(|((column, x), (row, y))| {
+						let i = (row * columns as u64 + column) as usize;
+						let labelled = labels.get(i).filter(|(label, _)| label.place(self.swatch.0, self.swatch.1).covers(x, y));
+						labelled.map(|&(_, color)| color).or(self.colors.get(i).copied())
+					})
+					.unwrap_or(self.gutter.1)
+			};
+			[color.r, color.g, color.b, color.a]
+		})
+	}
+
+}
+
+
+
+#[cfg(test)]
+mod tests {
+
+	use super::{ Palette, Layout };
+	use crate::{ Color, Error };
+
+	#[cfg(not(feature = "std"))]
+	use alloc::vec::Vec;
+
+
+
+	const COLORS: [Color; 5] = [Color::rgb(1, 1, 1), Color::rgb(2, 2, 2), Color::rgb(3, 3, 3), Color::rgb(4, 4, 4), Color::rgb(5, 5, 5)];
+	const GUTTER: Color = Color::rgb(9, 9, 9);
+
+	#[test]
+	fn test_dimensions () {
+		let palette = Palette::new(&COLORS).swatch_size(10, 20);
+		assert_eq!(palette.dimensions(), Ok((50, 20)));
+		assert_eq!(palette.clone().layout(Layout::Vertical).dimensions(), Ok((10, 100)));
+		assert_eq!(palette.clone().layout(Layout::Grid { columns: 2 }).gutter(1, GUTTER).border(3, GUTTER).dimensions(), Ok((27, 68)));
+		assert_eq!(palette.clone().layout(Layout::Grid { columns: 9 }).dimensions(), Ok((50, 20)));
+
+		assert!(matches!(Palette::new(&[]).dimensions(), Err(Error::UnsupportedOptions(_))));
+		assert!(matches!(palette.clone().layout(Layout::Grid { columns: 0 }).dimensions(), Err(Error::UnsupportedOptions(_))));
+		assert_eq!(palette.swatch_size(u32::MAX, 1).dimensions(), Err(Error::ImageTooLarge));
+	}
+
+	#[test]
+	fn test_shader () {
+		let palette = Palette::new(&COLORS).swatch_size(2, 1).layout(Layout::Grid { columns: 2 }).gutter(1, GUTTER).border(1, Color::rgb(0, 0, 0));
+		let (width, height) = palette.dimensions().unwrap();
+		let shader = palette.shader().unwrap();
+
+		let image = (0..height).map(|y| (0..width).map(|x| shader(x, y)[0]).collect::<Vec<u8>>()).collect::<Vec<_>>();
+		assert_eq!(image, [
+			[0, 0, 0, 0, 0, 0, 0],
+			[0, 1, 1, 9, 2, 2, 0],
+			[0, 9, 9, 9, 9, 9, 0],
+			[0, 3, 3, 9, 4, 4, 0],
+			[0, 9, 9, 9, 9, 9, 0],
+			[0, 5, 5, 9, 9, 9, 0],
+			[0, 0, 0, 0, 0, 0, 0]
+		]);
+	}
+
+	#[test]
+	fn test_pixels () {
+		assert_eq!(Palette::new(&COLORS[..2]).pixels().unwrap(), [1, 1, 1, 255, 2, 2, 2, 255]);
+		assert_eq!(Palette::new(&COLORS[..1]).gutter(1, GUTTER).border(1, Color::rgb(0, 0, 0)).pixels().unwrap().len(), 12);
+		assert_eq!(Palette::new(&COLORS[..3]).layout(Layout::Grid { columns: 2 }).pixels().unwrap().len(), 16);
+		assert_eq!(Palette::new(&COLORS[..2]).labels(true).pixels().unwrap()[8..], [255, 255, 255, 255, 255, 255, 255, 255]);
+	}
+
+	#[test]
+	fn test_labels () {
+		let colors = [Color::rgb(0, 0, 0), Color::rgb(255, 255, 0)];
+		let palette = Palette::new(&colors).swatch_size(43, 9).labels(true);
+		let shader = palette.shader().unwrap();
+
+		// the top left dot of `#`, in white on black and black on yellow
+		assert_eq!([shader(1, 2), shader(2, 2)], [[0, 0, 0, 255], [255, 255, 255, 255]]);
+		assert_eq!([shader(44, 2), shader(45, 2)], [[255, 255, 0, 255], [0, 0, 0, 255]]);
+	}
+
+}
@@ -0,0 +1,206 @@
+// repeating patterns, mostly as backdrops that make alpha visible
+
+use crate::{ Color, Error };
+
+
+
+/// which way [`Pattern::stripes`] run
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum Direction {
+	/// stripes along the x axis, stacked top to bottom
+	#[default]
+	Horizontal,
+	/// stripes along the y axis, side by side
+	Vertical,
+	/// stripes running from the bottom left to the top right
+	Diagonal
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Kind {
+	Checkerboard { cell: u32 },
+	Stripes { direction: Direction, width: u32 },
+	Dots { spacing: u32, radius: f64 }
+}
+
+
+
+/// a repeating two color pattern, for [`PngOptions::pattern`](crate::PngOptions::pattern)
+///
+/// ```
+/// use hexpng::{ PngOptions, Pattern, Direction, Color };
+///
+/// let stripes = Pattern::stripes(Direction::Diagonal, 4, Color::rgb(235, 35, 35), Color::rgb(255, 255, 255));
+/// let png = PngOptions::new().dimensions(64, 64).pattern(&stripes).unwrap();
+///
+/// // what #eb232380 looks like on a page
+/// let preview = Pattern::backdrop().overlay(Color::new(235, 35, 35, 128));
+/// let png = PngOptions::new().dimensions(64, 64).pattern(&preview).unwrap();
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct Pattern {
+	kind: Kind,
+	colors: (Color, Color),
+	overlay: Option<Color>
+}
+
+impl Pattern {
+
+	/// squares of `cell` x `cell` pixels alternating between the colors,
+	/// starting with `a` in the top left
+	pub fn checkerboard (cell: u32, a: Color, b: Color) -> Self {
+		Self { kind: Kind::Checkerboard { cell }, colors: (a, b), overlay: None }
+	}
+
+	/// the light gray and white checkerboard image editors show behind
+	/// transparent pixels
+	pub fn backdrop () -> Self {
+		Self::checkerboard(8, Color::rgb(204, 204, 204), Color::rgb(255, 255, 255))
+	}
+
+	/// stripes `width` pixels wide alternating between the colors,
+	/// starting with `a`
+	pub fn stripes (direction: Direction, width: u32, a: Color, b: Color) -> Self {
+		Self { kind: Kind::Stripes { direction, width }, colors: (a, b), overlay: None }
+	}
+
+	/// anti aliased dots of `radius` pixels centered in a grid of
+	/// `spacing` x `spacing` pixel cells
+	pub fn dots (spacing: u32, radius: f64, dot: Color, background: Color) -> Self {
+		Self { kind: Kind::Dots { spacing, radius }, colors: (dot, background), overlay: None }
+	}
+
+	/// composite a (translucent) color over the whole pattern, showing
+	/// what the color really looks like over it
+	pub fn overlay (mut self, color: Color) -> Self {
+		self.overlay = Some(color);
+		self
+	}
+
+	// a function giving the color of pixel (x, y)
+	pub(crate) fn shader (&self) -> Result<impl Fn(u32, u32) -> [u8; 4] + '_, Error> {
+		let size = match self.kind {
+			Kind::Checkerboard { cell } => cell,
+			Kind::Stripes { width, .. } => width,
+			Kind::Dots { spacing, .. } => spacing
+		};
+		if size == 0 {
+			return Err(Error::UnsupportedOptions("pattern cells have to be at least 1 pixel"));
+		}
+
+		let (a, b) = self.colors;
+
+		Ok(move |x: u32, y: u32| {
+			let color = match self.kind {
+				Kind::Checkerboard { cell } => if (x / cell + y / cell).is_multiple_of(2) { a } else { b },
+				Kind::Stripes { direction, width } => {
+					let position = match direction {
+						Direction::Horizontal => y as u64,
+						Direction::Vertical => x as u64,
+						Direction::Diagonal => x as u64 + y as u64
+					};
+					if (position / width as u64).is_multiple_of(2) { a } else { b }
+				},
+				Kind::Dots { spacing, radius } => {
+					let center = spacing as f64 / 2.0;
+					let (dx, dy) = ((x % spacing) as f64 + 0.5 - center, (y % spacing) as f64 + 0.5 - center);
+					// how much of the pixel the dot's edge covers, roughly
+					let coverage = (radius - libm::hypot(dx, dy) + 0.5).clamp(0.0, 1.0);
+					over(Color { a: (a.a as f64 * coverage + 0.5) as u8, ..a }, b)
+				}
+			};
+
+			let Color { r, g, b, a } = match self.overlay {
+				Some(overlay) => over(overlay, color),
+				None => color
+			};
+			[r, g, b, a]
+		})
+	}
+
+}
+
+
+
+// composite `top` over `bottom`, in srgb like browsers do
+pub(crate) fn over (top: Color, bottom: Color) -> Color {
+	let (top_alpha, bottom_alpha) = (top.a as u32, bottom.a as u32 * (255 - top.a as u32));
+	// alpha scaled by 255 * 255
+	let alpha = top_alpha * 255 + bottom_alpha;
+	if alpha == 0 {
+		return Color::new(0, 0, 0, 0);
+	}
+
+	let channel = |top_channel: u8, bottom_channel: u8| {
+		((top_channel as u32 * top_alpha * 255 + bottom_channel as u32 * bottom_alpha + alpha / 2) / alpha) as u8
+	};
+	Color::new(channel(top.r, bottom.r), channel(top.g, bottom.g), channel(top.b, bottom.b), ((alpha + 127) / 255) as u8)
+}
+
+
+
+#[cfg(test)]
+mod tests {
+
+	use super::{ Pattern, Direction, over };
+	use crate::{ Color, Error };
+
+
+
+	const BLACK: Color = Color::rgb(0, 0, 0);
+	const WHITE: Color = Color::rgb(255, 255, 255);
+
+	#[test]
+	fn test_checkerboard () {
+		let pattern = Pattern::checkerboard(2, BLACK, WHITE);
+		let shader = pattern.shader().unwrap();
+		let row = |y| [0, 1, 2, 3, 4].map(|x| shader(x, y)[0]);
+
+		assert_eq!(row(0), [0, 0, 255, 255, 0]);
+		assert_eq!(row(1), [0, 0, 255, 255, 0]);
+		assert_eq!(row(2), [255, 255, 0, 0, 255]);
+
+		assert!(matches!(Pattern::checkerboard(0, BLACK, WHITE).shader(), Err(Error::UnsupportedOptions(_))));
+	}
+
+	#[test]
+	fn test_stripes () {
+		let stripes = |direction| {
+			let pattern = Pattern::stripes(direction, 1, BLACK, WHITE);
+			let shader = pattern.shader().unwrap();
+			[(0, 0), (1, 0), (0, 1), (1, 1)].map(|(x, y)| shader(x, y)[0])
+		};
+
+		assert_eq!(stripes(Direction::Horizontal), [0, 0, 255, 255]);
+		assert_eq!(stripes(Direction::Vertical), [0, 255, 0, 255]);
+		assert_eq!(stripes(Direction::Diagonal), [0, 255, 255, 0]);
+	}
+
+	#[test]
+	fn test_dots () {
+		let pattern = Pattern::dots(10, 2.5, BLACK, WHITE);
+		let shader = pattern.shader().unwrap();
+
+		assert_eq!(shader(5, 5), [0, 0, 0, 255]);
+		assert_eq!(shader(0, 0), [255, 255, 255, 255]);
+		assert_eq!(shader(15, 25), [0, 0, 0, 255]);
+		// anti aliased edge
+		assert!((1..255).contains(&shader(7, 5)[0]));
+	}
+
+	#[test]
+	fn test_over () {
+		let red = Color::rgb(255, 0, 0);
+
+		assert_eq!(over(Color::new(255, 0, 0, 128), WHITE), Color::rgb(255, 127, 127));
+		assert_eq!(over(red, WHITE), red);
+		assert_eq!(over(Color::new(0, 0, 0, 0), red), red);
+		assert_eq!(over(Color::new(255, 0, 0, 128), Color::new(0, 0, 0, 0)), Color::new(255, 0, 0, 128));
+		assert_eq!(over(Color::new(0, 0, 0, 0), Color::new(0, 0, 0, 0)), Color::new(0, 0, 0, 0));
+
+		let preview = Pattern::backdrop().overlay(Color::new(0, 0, 0, 128));
+		let shader = preview.shader().unwrap();
+		assert_eq!([shader(0, 0), shader(8, 0)], [[102, 102, 102, 255], [127, 127, 127, 255]]);
+	}
+
+}
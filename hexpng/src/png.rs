@@ -0,0 +1,1227 @@
+
+use crate::{ Error, Color, ColorType, Compression, Filter, Label, PngWriter, Sink, Strategy, Text };
+use crate::{ Chromaticities, Cicp, IccProfile, RenderingIntent, Resolution, Timestamp, Animation, Gradient, Identicon, Palette, Pattern };
+use crate::{ crc32, filter, interlace, color_space::{ self, ColorSpace }, deflate, format::Format };
+use crate::apng::{ self, FrameControl, Sequence };
+
+#[cfg(not(feature = "std"))]
+use alloc::{ vec, vec::Vec };
+
+
+
+/// the largest width/height a png is allowed to have i.e. 2^31-1
+pub const MAX_DIMENSION: u32 = (1 << 31) - 1;
+
+const DEFAULT_WIDTH : u32 = 10;
+const DEFAULT_HEIGHT: u32 = 10;
+// the same as libpng
+const DEFAULT_IDAT_SIZE: u32 = 8192;
+
+// the png spec limits chunks to 2^31-1 bytes of data
+pub(crate) const MAX_CHUNK_LEN: u32 = (1 << 31) - 1;
+
+pub(crate) const HEADER: &[u8] = b"\x89PNG\r\n\x1A\n";
+
+
+
+/// options for generating a png
+///
+/// unless a color type and bit depth are set with [`PngOptions::format`],
+/// the smallest one that can store the image losslessly is used
+///
+/// ```
+/// use hexpng::PngOptions;
+///
+/// let swatch = PngOptions::new().dimensions(512, 512).generate(235, 35, 35, 255).unwrap();
+/// let pixel  = PngOptions::new().dimensions(1, 1).generate(235, 35, 35, 127).unwrap();
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PngOptions {
+	pub(crate) width: u32,
+	pub(crate) height: u32,
+	format: Option<(ColorType, u8)>,
+	pub(crate) filter: Filter,
+	pub(crate) compression: Compression,
+	pub(crate) strategy: Strategy,
+	optimize: bool,
+	idat_size: u32,
+	pub(crate) interlace: bool,
+	color_space: ColorSpace,
+	resolution: Option<Resolution>,
+	modified: Option<Timestamp>,
+	text: Vec<Text>,
+	label: Option<Label>
+}
+
+impl Default for PngOptions {
+	fn default () -> Self {
+		Self::new()
+	}
+}
+
+impl PngOptions {
+
+	/// options for a 10x10 png
+	pub fn new () -> Self {
+		Self {
+			width: DEFAULT_WIDTH,
+			height: DEFAULT_HEIGHT,
+			format: None,
+			filter: Filter::None,
+			compression: Compression::default(),
+			strategy: Strategy::Default,
+			optimize: false,
+			idat_size: DEFAULT_IDAT_SIZE,
+			interlace: false,
+			color_space: ColorSpace::default(),
+			resolution: None,
+			modified: None,
+			text: Vec::new(),
+			label: None
+		}
+	}
+
+	/// set the width of the png in pixels
+	pub fn width (mut self, width: u32) -> Self {
+		self.width = width;
+		self
+	}
+
+	/// set the height of the png in pixels
+	pub fn height (mut self, height: u32) -> Self {
+		self.height = height;
+		self
+	}
+
+	/// set both the width and height of the png in pixels
+	pub fn dimensions (self, width: u32, height: u32) -> Self {
+		self.width(width).height(height)
+	}
+
+	/// store the png with this color type and bit depth instead of
+	/// picking the smallest one
+	///
+	/// generating fails with [`Error::UnsupportedOptions`] if the png
+	/// spec doesn't allow the combination or if the image can't be
+	/// stored losslessly with it e.g. a red swatch as grayscale
+	pub fn format (mut self, color_type: ColorType, bit_depth: u8) -> Self {
+		self.format = Some((color_type, bit_depth));
+		self
+	}
+
+	/// set how scanlines are filtered before compression, [`Filter::None`]
+	/// by default
+	pub fn filter (mut self, filter: Filter) -> Self {
+		self.filter = filter;
+		self
+	}
+
+	/// set how hard the image data is compressed, [`Compression::Level`]
+	/// 6 by default
+	pub fn compression (mut self, compression: Compression) -> Self {
+		self.compression = compression;
+		self
+	}
+
+	/// set what kind of matches the image data is compressed with,
+	/// [`Strategy::Default`] by default
+	pub fn strategy (mut self, strategy: Strategy) -> Self {
+		self.strategy = strategy;
+		self
+	}
+
+	/// compress the image data with every filter and every strategy
+	/// (except [`Strategy::Stored`]) and keep the smallest result,
+	/// instead of the ones set with [`PngOptions::filter`] and
+	/// [`PngOptions::strategy`]
+	///
+	/// this is slow, every combination being tried for every candidate
+	/// color type, but the png is never larger than with any of them
+	///
+	/// ```
+	/// use hexpng::{ PngOptions, Compression };
+	///
+	/// // as small as hexpng can make it
+	/// let png = PngOptions::new().compression(Compression::Max).optimize(true).generate(235, 35, 35, 255).unwrap();
+	/// ```
+	pub fn optimize (mut self, optimize: bool) -> Self {
+		self.optimize = optimize;
+		self
+	}
+
+	/// split the compressed image data into IDAT (and fdAT) chunks of at
+	/// most `size` bytes, 8192 by default like libpng
+	///
+	/// every chunk costs 12 bytes, but decoders reading the png as it
+	/// arrives can start on smaller chunks sooner
+	pub fn idat_size (mut self, size: u32) -> Self {
+		self.idat_size = size;
+		self
+	}
+
+	/// store the png adam7 interlaced, so it can be shown progressively
+	/// while it's still downloading, at the cost of a slightly larger file
+	pub fn interlace (mut self, interlace: bool) -> Self {
+		self.interlace = interlace;
+		self
+	}
+
+	/// tag the png as srgb, the color space css colors are in, with an
+	/// sRGB chunk plus matching gAMA and cHRM chunks for viewers that
+	/// don't understand sRGB
+	pub fn tag_srgb (self) -> Self {
+		self.srgb(RenderingIntent::Perceptual).gamma(1.0 / 2.2).chromaticities(Chromaticities::SRGB)
+	}
+
+	/// mark the samples as srgb with this rendering intent in an sRGB
+	/// chunk, which can't be combined with an [icc profile](PngOptions::icc_profile)
+	pub fn srgb (mut self, intent: RenderingIntent) -> Self {
+		self.color_space.srgb = Some(intent);
+		self
+	}
+
+	/// set the gamma the samples were encoded with in a gAMA chunk, e.g.
+	/// `1.0 / 2.2`, stored with 5 decimal places, generating the png fails
+	/// with [`Error::InvalidChunk`] if the gamma rounds to 0 or less
+	pub fn gamma (mut self, gamma: f64) -> Self {
+		self.color_space.gamma = Some(color_space::fixed_point(gamma));
+		self
+	}
+
+	/// set the white point and primaries in a cHRM chunk
+	pub fn chromaticities (mut self, chromaticities: Chromaticities) -> Self {
+		self.color_space.chromaticities = Some(chromaticities);
+		self
+	}
+
+	/// embed an icc profile in an iCCP chunk
+	pub fn icc_profile (mut self, icc_profile: IccProfile) -> Self {
+		self.color_space.icc_profile = Some(icc_profile);
+		self
+	}
+
+	/// set the color space with itu-t h.273 code points in a cICP chunk
+	pub fn cicp (mut self, cicp: Cicp) -> Self {
+		self.color_space.cicp = Some(cicp);
+		self
+	}
+
+	/// set the physical size of the pixels in a pHYs chunk, e.g.
+	/// [`Resolution::dpi`] for print
+	pub fn resolution (mut self, resolution: Resolution) -> Self {
+		self.resolution = Some(resolution);
+		self
+	}
+
+	/// set the last time the image was modified in a tIME chunk, e.g.
+	/// [`Timestamp::now`]
+	pub fn modified (mut self, modified: Timestamp) -> Self {
+		self.modified = Some(modified);
+		self
+	}
+
+	/// embed a keyword and text pair, can be called more than once
+	pub fn text (mut self, text: Text) -> Self {
+		self.text.push(text);
+		self
+	}
+
+	/// draw a line of text, like the color's hexcode, in the middle of
+	/// generated, gradient, pattern and palette images
+	pub fn label (mut self, label: Label) -> Self {
+		self.label = Some(label);
+		self
+	}
+
+	/// generate solid/translucent png data with these options
+	///
+	/// the dimensions are checked before anything is allocated,
+	/// returning [`Error::InvalidDimensions`] if they're outside the
+	/// png limits or [`Error::ImageTooLarge`] if the image doesn't
+	/// fit in memory
+	pub fn generate (&self, r: u8, g: u8, b: u8, a: u8) -> Result<Vec<u8>, Error> {
+		let pixel = [r, g, b, a];
+		if self.label.is_some() {
+			return self.render(|_, _| pixel);
+		}
+
+		self.smallest(&pixel, |format| {
+			let data = generate_data(pixel, self.width, self.height, format)?;
+			self.assemble(format, data)
+		})
+	}
+
+	/// stream solid/translucent png data with these options to `sink`,
+	/// holding on to a row of it instead of the whole png
+	///
+	/// the color type is the one set with [`PngOptions::format`], or the
+	/// one with the fewest bits per pixel that can store the color, since
+	/// finding the smallest png would mean building every candidate.
+	/// labelled and interlaced pngs are still built in memory first
+	///
+	/// ```
+	/// use hexpng::PngOptions;
+	///
+	/// let mut file = Vec::new();
+	/// PngOptions::new().dimensions(4096, 4096).generate_to(&mut file, 235, 35, 35, 255).unwrap();
+	/// ```
+	pub fn generate_to<S: Sink> (&self, mut sink: S, r: u8, g: u8, b: u8, a: u8) -> Result<S, Error> {
+		let pixel = [r, g, b, a];
+		if self.label.is_some() || self.interlace {
+			sink.write_bytes(&self.generate(r, g, b, a)?)?;
+			return Ok(sink);
+		}
+
+		let format = match self.format {
+			Some((color_type, bit_depth)) => Format::requested(&pixel, color_type, bit_depth)?,
+			None => Format::candidates(&pixel).into_iter()
+				.min_by_key(|format| format.color_type.bits_per_pixel(format.bit_depth))
+				.ok_or(Error::UnsupportedOptions("no color type can store the image"))?
+		};
+
+		let mut writer = PngWriter::new(sink, self, format.clone())?;
+		let mut row = Vec::new();
+		solid_row(pixel, self.width, &format, &mut row);
+		for _ in 0..self.height {
+			writer.write_row(&row)?;
+		}
+		writer.finish()
+	}
+
+	/// start a png with these options' dimensions that's written to
+	/// `sink` a row at a time, see [`PngWriter`]
+	///
+	/// the rows are packed like a [`RawImage`]'s, so the color type set
+	/// with [`PngOptions::format`] isn't used
+	pub fn writer<S: Sink> (&self, sink: S, color_type: ColorType, bit_depth: u8) -> Result<PngWriter<S>, Error> {
+		PngWriter::new(sink, self, Format { color_type, bit_depth, palette: Vec::new(), transparent: None })
+	}
+
+	/// encode a caller supplied image with these options' dimensions
+	///
+	/// the pixels are stored exactly as they are, so the color type
+	/// set with [`PngOptions::format`] isn't used
+	///
+	/// ```
+	/// use hexpng::{ PngOptions, RawImage, ColorType };
+	///
+	/// // a 2x2 black and white checkerboard, 1 bit per pixel
+	/// let pixels = [0b0100_0000, 0b1000_0000];
+	/// let png = PngOptions::new().dimensions(2, 2).encode(&RawImage::new(&pixels, ColorType::Grayscale, 1)).unwrap();
+	/// ```
+	pub fn encode (&self, image: &RawImage) -> Result<Vec<u8>, Error> {
+		let format = image.format()?;
+		raw_data_len(self.width, self.height, &format)?;
+
+		let row_len = format.row_len(self.width).ok_or(Error::ImageTooLarge)?;
+		let stride = image.stride.unwrap_or(row_len);
+		if stride < row_len {
+			return Err(Error::InvalidImage("stride is shorter than a row"));
+		}
+
+		let needed = stride.checked_mul(self.height as usize - 1)
+			.and_then(|len| len.checked_add(row_len))
+			.filter(|&len| len <= image.data.len())
+			.ok_or(Error::InvalidImage("pixel buffer is shorter than the image"))?;
+		if format.color_type == ColorType::Indexed && !format.valid_indices(&image.data[..needed], stride, row_len, self.width) {
+			return Err(Error::InvalidImage("pixel refers to a palette entry that doesn't exist"));
+		}
+
+		let data = scanlines(self.width, self.height, &format, |y, out| {
+			out.extend_from_slice(&image.data[y as usize * stride..][..row_len]);
+		})?;
+
+		self.assemble(&format, data)
+	}
+
+	/// encode tightly packed 8 bit rgba pixels with these options,
+	/// using the smallest color type unless one was set with
+	/// [`PngOptions::format`]
+	pub fn encode_rgba8 (&self, pixels: &[u8]) -> Result<Vec<u8>, Error> {
+		let row_len = (self.width as usize).checked_mul(4).ok_or(Error::ImageTooLarge)?;
+		if row_len.checked_mul(self.height as usize) != Some(pixels.len()) {
+			return Err(Error::InvalidImage("pixel buffer doesn't match the image dimensions"));
+		}
+
+		self.smallest(pixels, |format| {
+			let data = scanlines(self.width, self.height, format, |y, out| {
+				format.pack_row(&pixels[y as usize * row_len..][..row_len], out);
+			})?;
+			self.assemble(format, data)
+		})
+	}
+
+	/// render a gradient with these options' dimensions
+	pub fn gradient (&self, gradient: &Gradient) -> Result<Vec<u8>, Error> {
+		self.render(gradient.shader(self.width, self.height)?)
+	}
+
+	/// render a repeating pattern with these options' dimensions
+	pub fn pattern (&self, pattern: &Pattern) -> Result<Vec<u8>, Error> {
+		self.render(pattern.shader()?)
+	}
+
+	/// render a palette of color swatches, the dimensions come from the
+	/// palette's layout instead of these options
+	///
+	/// unless a color type is set with [`PngOptions::format`] or a
+	/// [label](PngOptions::label) is drawn over the whole palette,
+	/// palettes with at most 256 distinct colors are always stored indexed
+	pub fn palette (&self, palette: &Palette) -> Result<Vec<u8>, Error> {
+		let (width, height) = palette.dimensions()?;
+		let mut options = self.clone().dimensions(width, height);
+
+		// a label's text color could be blended with the swatches
+		if options.format.is_none() && self.label.is_none() {
+			if let Some(format) = Format::indexed(&palette.pixels()?) {
+				options = options.format(ColorType::Indexed, format.bit_depth);
+			}
+		}
+
+		options.render(palette.shader()?)
+	}
+
+	/// render an identicon with these options' dimensions, the grid is
+	/// centered in the largest square that fits
+	pub fn identicon (&self, identicon: &Identicon) -> Result<Vec<u8>, Error> {
+		self.render(identicon.shader(self.width, self.height)?)
+	}
+
+	/// generate an opaque preview of a (translucent) color, composited
+	/// over the [checkerboard](Pattern::backdrop) image editors show
+	/// behind transparent pixels
+	pub fn generate_preview (&self, r: u8, g: u8, b: u8, a: u8) -> Result<Vec<u8>, Error> {
+		self.pattern(&Pattern::backdrop().overlay(Color::new(r, g, b, a)))
+	}
+
+	/// encode an animated png (apng) with these options, each frame
+	/// stored with the same color type
+	///
+	/// the first frame has to cover the whole png, as it's also what
+	/// viewers that don't support animation show
+	pub fn encode_animation (&self, animation: &Animation) -> Result<Vec<u8>, Error> {
+		if animation.frames.is_empty() {
+			return Err(Error::InvalidImage("an animation needs at least one frame"));
+		}
+		for (i, frame) in animation.frames.iter().enumerate() {
+			frame.validate(self.width, self.height, i == 0)?;
+		}
+
+		let pixels = animation.frames.iter().flat_map(|frame| frame.pixels.iter().copied()).collect::<Vec<u8>>();
+
+		self.smallest(&pixels, |format| {
+			let frames = animation.frames.iter().map(|frame| {
+				let row_len = frame.control.width as usize * 4;
+				let data = scanlines(frame.control.width, frame.control.height, format, |y, out| {
+					format.pack_row(&frame.pixels[y as usize * row_len..][..row_len], out);
+				})?;
+				Ok((frame.control, data))
+			});
+			self.assemble_animation(format, animation.plays, frames)
+		})
+	}
+
+	/// generate an animated png that fades through `colors` in `frames`
+	/// frames, each shown for `delay` (numerator, denominator) seconds,
+	/// looping forever, failing with [`Error::ImageTooLarge`] when that
+	/// many frames don't fit in memory
+	///
+	/// ```
+	/// use hexpng::{ PngOptions, Color };
+	///
+	/// let colors = [Color::rgb(235, 35, 35), Color::rgb(35, 35, 235)];
+	/// let fade = PngOptions::new().dimensions(64, 64).transition(&colors, 30, (1, 30)).unwrap();
+	/// ```
+	pub fn transition (&self, colors: &[Color], frames: u32, delay: (u16, u16)) -> Result<Vec<u8>, Error> {
+		let steps = apng::interpolate(colors, frames)?;
+		let mut pixels = Vec::new();
+		pixels.try_reserve_exact(steps.len() * 4).map_err(|_| Error::ImageTooLarge)?;
+		pixels.extend(steps.iter().flatten());
+
+		self.smallest(&pixels, |format| {
+			let frames = steps.iter().map(|&pixel| {
+				let control = FrameControl { delay, ..FrameControl::new(self.width, self.height) };
+				Ok((control, generate_data(pixel, self.width, self.height, format)?))
+			});
+			self.assemble_animation(format, 0, frames)
+		})
+	}
+
+	// encode the rgba8 image where pixel (x, y) is `pixel(x, y)`
+	pub(crate) fn render<F> (&self, pixel: F) -> Result<Vec<u8>, Error>
+	where
+		F: Fn(u32, u32) -> [u8; 4]
+	{
+		if self.width == 0 || self.height == 0 || self.width > MAX_DIMENSION || self.height > MAX_DIMENSION {
+			return Err(Error::InvalidDimensions { width: self.width, height: self.height });
+		}
+
+		let len = (self.width as usize).checked_mul(self.height as usize)
+			.and_then(|pixels| pixels.checked_mul(4))
+			.ok_or(Error::ImageTooLarge)?;
+		let mut pixels = Vec::new();
+		pixels.try_reserve_exact(len).map_err(|_| Error::ImageTooLarge)?;
+
+		for y in 0..self.height {
+			for x in 0..self.width {
+				pixels.extend(pixel(x, y));
+			}
+		}
+		if let Some(label) = &self.label {
+			label.draw(&mut pixels, self.width, self.height);
+		}
+
+		self.encode_rgba8(&pixels)
+	}
+
+	// encode the rgba8 `pixels` with the requested format, or with
+	// every candidate format keeping the smallest png
+	fn smallest<F> (&self, pixels: &[u8], encode: F) -> Result<Vec<u8>, Error>
+	where
+		F: Fn(&Format) -> Result<Vec<u8>, Error>
+	{
+		let formats = match self.format {
+			Some((color_type, bit_depth)) => vec![Format::requested(pixels, color_type, bit_depth)?],
+			None => Format::candidates(pixels)
+		};
+
+		let mut smallest: Option<Vec<u8>> = None;
+		for format in &formats {
+			let png = encode(format)?;
+			if smallest.as_ref().is_none_or(|s| png.len() < s.len()) {
+				smallest = Some(png);
+			}
+		}
+
+		smallest.ok_or(Error::UnsupportedOptions("no color type can store the image"))
+	}
+
+	// put together the png from the raw image data
+	fn assemble (&self, format: &Format, data: Vec<u8>) -> Result<Vec<u8>, Error> {
+		let mut png = self.header(format)?;
+
+		for part in self.split(&self.compress(format, data, self.width, self.height)?)? {
+			png.extend(chunk(b"IDAT", part)?);
+		}
+		png.extend(chunk(b"IEND", b"")?);
+
+		Ok(png)
+	}
+
+	// put together an apng from the frame controls and raw image data
+	// of every frame
+	fn assemble_animation<I> (&self, format: &Format, plays: u32, frames: I) -> Result<Vec<u8>, Error>
+	where
+		I: ExactSizeIterator<Item = Result<(FrameControl, Vec<u8>), Error>>
+	{
+		let count = u32::try_from(frames.len()).map_err(|_| Error::UnsupportedOptions("an apng can't have more than 2^32-1 frames"))?;
+		let mut png = self.header(format)?;
+		let mut sequence = Sequence::default();
+
+		png.extend(chunk(b"acTL", &apng::actl_data(count, plays))?);
+		for (i, frame) in frames.enumerate() {
+			let (control, data) = frame?;
+			png.extend(sequence.chunk(b"fcTL", &control.data())?);
+
+			// many frames add up, fail instead of aborting when the png doesn't fit
+			let compressed = self.compress(format, data, control.width, control.height)?;
+			png.try_reserve(compressed.len() + 64).map_err(|_| Error::ImageTooLarge)?;
+			for part in self.split(&compressed)? {
+				if i == 0 {
+					png.extend(chunk(b"IDAT", part)?);
+				} else {
+					png.extend(sequence.chunk(b"fdAT", part)?);
+				}
+			}
+		}
+		png.extend(chunk(b"IEND", b"")?);
+
+		Ok(png)
+	}
+
+	// compressed image data in parts of at most `idat_size` bytes
+	fn split<'a> (&self, data: &'a [u8]) -> Result<core::slice::Chunks<'a, u8>, Error> {
+		Ok(data.chunks(self.idat_len()?))
+	}
+
+	// the most bytes of image data an IDAT chunk holds
+	pub(crate) fn idat_len (&self) -> Result<usize, Error> {
+		if self.idat_size == 0 || self.idat_size > MAX_CHUNK_LEN {
+			return Err(Error::UnsupportedOptions("IDAT chunks have to hold 1 to 2^31-1 bytes"));
+		}
+		Ok(self.idat_size as usize)
+	}
+
+	// the signature and every chunk that comes before the image data
+	pub(crate) fn header (&self, format: &Format) -> Result<Vec<u8>, Error> {
+		let mut png = HEADER.to_vec();
+
+		png.extend(chunk(b"IHDR", &ihdr_data(self.width, self.height, format.bit_depth, format.color_type as u8, self.interlace))?);
+		for (chunk_type, data) in self.color_space.chunks()? {
+			png.extend(chunk(chunk_type, &data)?);
+		}
+		if let Some(plte) = format.plte_data() {
+			png.extend(chunk(b"PLTE", &plte)?);
+		}
+		if let Some(trns) = format.trns_data() {
+			png.extend(chunk(b"tRNS", &trns)?);
+		}
+		if let Some(resolution) = self.resolution {
+			let (chunk_type, data) = resolution.chunk()?;
+			png.extend(chunk(chunk_type, &data)?);
+		}
+		if let Some(modified) = self.modified {
+			let (chunk_type, data) = modified.chunk();
+			png.extend(chunk(chunk_type, &data)?);
+		}
+		for text in &self.text {
+			png.extend(chunk(text.chunk_type(), &text.chunk_data()?)?);
+		}
+
+		Ok(png)
+	}
+
+	// filter and compress the raw image data of a `width` x `height` image
+	fn compress (&self, format: &Format, data: Vec<u8>, width: u32, height: u32) -> Result<Vec<u8>, Error> {
+		let bits_per_pixel = format.color_type.bits_per_pixel(format.bit_depth);
+		let bpp = bits_per_pixel.div_ceil(8);
+
+		// every pass of an interlaced image is filtered like a separate image
+		let (data, images) = if self.interlace {
+			(
+				interlace::interlace(&data, width, height, bits_per_pixel),
+				interlace::passes(width, height, bits_per_pixel).collect::<Vec<_>>()
+			)
+		} else {
+			let row_len = format.row_len(width).ok_or(Error::ImageTooLarge)?;
+			(data, vec![(row_len, height as usize)])
+		};
+
+		let filtered = |filter: Filter| {
+			let mut data = data.clone();
+			let mut rest = &mut data[..];
+			for &(row_len, rows) in &images {
+				let (image, next) = rest.split_at_mut((row_len + 1) * rows);
+				filter::apply(filter, image, row_len, bpp);
+				rest = next;
+			}
+			data
+		};
+
+		let filters = match (self.optimize, self.filter) {
+			(true, _) | (false, Filter::BruteForce) => &Filter::BRUTE_FORCE[..],
+			(false, filter) => &[filter][..]
+		};
+		let strategies = match self.optimize {
+			true => &Strategy::OPTIMIZE[..],
+			false => &[self.strategy][..]
+		};
+
+		let mut smallest: Option<Vec<u8>> = None;
+		for &filter in filters {
+			let filtered = match filter {
+				Filter::None => None,
+				filter => Some(filtered(filter))
+			};
+			let data = filtered.as_deref().unwrap_or(&data);
+			for &strategy in strategies {
+				let compressed = deflate::zlib_with(data, self.compression, strategy);
+				if smallest.as_ref().is_none_or(|s| compressed.len() < s.len()) {
+					smallest = Some(compressed);
+				}
+			}
+		}
+		smallest.ok_or(Error::Compression)
+	}
+
+}
+
+
+
+/// pixels laid out exactly like a png stores them, for [`PngOptions::encode`]
+///
+/// rows of samples are packed most significant bit first, 16 bit samples
+/// are big endian, and each row starts on a byte boundary
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RawImage<'a> {
+	data: &'a [u8],
+	color_type: ColorType,
+	bit_depth: u8,
+	stride: Option<usize>,
+	palette: &'a [Color]
+}
+
+impl<'a> RawImage<'a> {
+
+	/// pixels of `color_type` with `bit_depth` bits per sample, one row
+	/// right after another
+	pub fn new (data: &'a [u8], color_type: ColorType, bit_depth: u8) -> Self {
+		Self { data, color_type, bit_depth, stride: None, palette: &[] }
+	}
+
+	/// set the number of bytes from the start of one row to the start of
+	/// the next, when rows are padded
+	pub fn stride (mut self, stride: usize) -> Self {
+		self.stride = Some(stride);
+		self
+	}
+
+	/// set the palette of an indexed image, entries that aren't fully
+	/// opaque are stored in a tRNS chunk
+	pub fn palette (mut self, palette: &'a [Color]) -> Self {
+		self.palette = palette;
+		self
+	}
+
+	fn format (&self) -> Result<Format, Error> {
+		let format = Format {
+			color_type: self.color_type,
+			bit_depth: self.bit_depth,
+			palette: self.palette.iter().map(|c| [c.r, c.g, c.b, c.a]).collect(),
+			transparent: None
+		};
+		format.validate()?;
+		Ok(format)
+	}
+
+}
+
+
+
+/// function to generate solid/translucent png data from a hexcode
+///
+/// as the signature suggests, this function requires 4 u8
+/// values i.e. 0-255 representing the lowest and highest
+/// intensities of the red, green, blue channels and opacity
+/// in the alpha channel respectively
+///
+/// the png is 10x10 pixels, use [`PngOptions`] for other sizes
+pub fn generate_png (r: u8, g: u8, b: u8, a: u8) -> Result<Vec<u8>, Error> {
+	PngOptions::new().generate(r, g, b, a)
+}
+
+
+
+// length in bytes of the raw (filter byte + packed pixels) image data
+fn raw_data_len (w: u32, h: u32, format: &Format) -> Result<usize, Error> {
+	if w == 0 || h == 0 || w > MAX_DIMENSION || h > MAX_DIMENSION {
+		return Err(Error::InvalidDimensions { width: w, height: h });
+	}
+
+	format.row_len(w)
+		.and_then(|row| row.checked_add(1))
+		.and_then(|row| row.checked_mul(usize::try_from(h).ok()?))
+		.filter(|&len| len <= isize::MAX as usize)
+		.ok_or(Error::ImageTooLarge)
+}
+
+// filter byte + packed pixels of every row, `row` appends the packed pixels of row `y`
+fn scanlines<F> (w: u32, h: u32, format: &Format, mut row: F) -> Result<Vec<u8>, Error>
+where
+	F: FnMut(u32, &mut Vec<u8>)
+{
+	let mut data = Vec::new();
+	data.try_reserve_exact(raw_data_len(w, h, format)?).map_err(|_| Error::ImageTooLarge)?;
+
+	for y in 0..h {
+		data.push(0u8);
+		row(y, &mut data);
+	}
+
+	Ok(data)
+}
+
+fn generate_data (pixel: [u8; 4], w: u32, h: u32, format: &Format) -> Result<Vec<u8>, Error> {
+	let mut data = Vec::new();
+	data.try_reserve_exact(raw_data_len(w, h, format)?).map_err(|_| Error::ImageTooLarge)?;
+
+	// build the first scanline, then copy it for the rest
+	data.push(0u8);
+	solid_row(pixel, w, format, &mut data);
+
+	let row_len = data.len();
+	for _ in 1..h {
+		data.extend_from_within(..row_len);
+	}
+
+	Ok(data)
+}
+
+// append a `w` pixel row of `pixel` packed with `format` to `out`
+fn solid_row (pixel: [u8; 4], w: u32, format: &Format, out: &mut Vec<u8>) {
+	// a block of 64 pixels always packs into whole bytes, whatever the bit depth
+	const BLOCK: usize = 64;
+
+	let rgba = pixel.repeat(BLOCK);
+	let mut block = Vec::new();
+	format.pack_row(&rgba, &mut block);
+
+	for _ in 0..w as usize / BLOCK {
+		out.extend_from_slice(&block);
+	}
+	format.pack_row(&rgba[..w as usize % BLOCK * 4], out);
+}
+
+// Image Header Chunk
+fn ihdr_data (width: u32, height: u32, bit_depth: u8, color_type: u8, interlace: bool) -> Vec<u8> {
+	[
+		width.to_be_bytes().to_vec(),
+		height.to_be_bytes().to_vec(),
+		vec![bit_depth],
+		vec![color_type],
+		vec![0u8; 2], // compression, filter
+		vec![interlace as u8]
+	].concat()
+}
+
+// type and data of a chunk that still has to be assembled
+pub(crate) type Chunk = (&'static [u8; 4], Vec<u8>);
+
+pub(crate) fn chunk (chunk_type: &[u8], data: &[u8]) -> Result<Vec<u8>, Error> {
+	let length = u32::try_from(data.len()).ok()
+		.filter(|&length| length <= MAX_CHUNK_LEN)
+		.ok_or(Error::InvalidChunk("chunks can't hold more than 2^31-1 bytes"))?;
+
+	Ok([
+		length.to_be_bytes().to_vec(),
+		chunk_type.to_vec(),
+		data.to_vec(),
+		chunk_checksum(chunk_type, data).to_be_bytes().to_vec()
+	].concat())
+}
+
+pub(crate) fn chunk_checksum (chunk_type: &[u8], data: &[u8]) -> u32 {
+	let mut hasher = crc32::Hasher::new();
+	hasher.append(chunk_type);
+	hasher.append(data);
+	hasher.checksum()
+}
+
+
+
+#[cfg(test)]
+mod tests {
+
+	use crate::deflate::unzlib;
+	use crate::decode::{ image_data, expand };
+
+	use super::{ PngOptions, RawImage, MAX_DIMENSION };
+	use crate::{ Error, Color, ColorType, Compression, Filter, Label, Strategy, Text, Gradient, Identicon, Palette, Pattern, Animation, Frame, BlendOp, DisposeOp, Cicp, IccProfile, RenderingIntent, Resolution, Timestamp, Chunk, Header, decode };
+
+	#[cfg(not(feature = "std"))]
+	use alloc::vec::Vec;
+
+
+
+	// every chunk of a png, with its crc checked
+	fn chunks (png_data: &[u8]) -> Vec<Chunk<'_>> {
+		crate::chunks(png_data).unwrap().collect::<Result<_, _>>().unwrap()
+	}
+
+	// rgba pixels of an apng frame, a `width` x `height` image stored
+	// like the png's own in a fcTL chunk's fdAT chunk
+	fn frame (png_data: &[u8], fdat: &Chunk, width: u32, height: u32) -> Vec<u8> {
+		let header = Header { width, height, ..Header::parse(chunks(png_data)[0].data).unwrap() };
+		let data = image_data(&header, &fdat.data[4..], usize::MAX).unwrap();
+		expand(&header, &data, None, None).unwrap()
+	}
+
+	#[test]
+	fn test_arbitrary_dimensions () {
+		for (w, h) in [(1, 1), (512, 512), (3, 7)] {
+			let image = decode(&PngOptions::new().dimensions(w, h).generate(235, 35, 35, 127).unwrap()).unwrap();
+			assert_eq!((image.header.width, image.header.height), (w, h), "png dimensions doesn't match");
+			assert_eq!(image.pixels, [235, 35, 35, 127].repeat((w * h) as usize));
+		}
+	}
+
+	#[test]
+	fn test_encode_raw_image () {
+		let options = PngOptions::new().dimensions(2, 2);
+
+		let padded = [0b0100_0000, 0xaa, 0b1000_0000, 0xbb];
+		let image = decode(&options.encode(&RawImage::new(&padded, ColorType::Grayscale, 1).stride(2)).unwrap()).unwrap();
+		assert_eq!((image.header.bit_depth, image.header.color_type), (1, ColorType::Grayscale));
+		assert_eq!(image.pixels, [[0, 0, 0, 255], [255; 4], [255; 4], [0, 0, 0, 255]].concat());
+
+		// high and low bytes match, so the samples round to them exactly
+		let rgba16 = (0..16).flat_map(|i| [i * 16, i * 16]).collect::<Vec<u8>>();
+		let image = decode(&options.encode(&RawImage::new(&rgba16, ColorType::Rgba, 16)).unwrap()).unwrap();
+		assert_eq!((image.header.bit_depth, image.header.color_type), (16, ColorType::Rgba));
+		assert_eq!(image.pixels, (0..16).map(|i| i * 16).collect::<Vec<u8>>());
+
+		let palette = [Color::rgb(235, 35, 35), Color::new(0, 0, 0, 0), Color::rgb(255, 255, 255)];
+		let indices = [0b0001_0000, 0b1000_0000];
+		let png_data = options.encode(&RawImage::new(&indices, ColorType::Indexed, 2).palette(&palette)).unwrap();
+		let chunks = chunks(&png_data);
+		assert_eq!(chunks.iter().map(|chunk| chunk.chunk_type).collect::<Vec<_>>(), [*b"IHDR", *b"PLTE", *b"tRNS", *b"IDAT", *b"IEND"]);
+		assert_eq!(chunks[1].data, [235, 35, 35, 0, 0, 0, 255, 255, 255]);
+		assert_eq!(chunks[2].data, [255, 0]);
+		assert_eq!(decode(&png_data).unwrap().pixels, [[235, 35, 35, 255], [0, 0, 0, 0], [255, 255, 255, 255], [235, 35, 35, 255]].concat());
+	}
+
+	#[test]
+	fn test_encode_invalid_raw_image () {
+		let options = PngOptions::new().dimensions(2, 2);
+		let palette = [Color::rgb(0, 0, 0), Color::rgb(255, 255, 255)];
+
+		assert!(matches!(options.encode(&RawImage::new(&[0; 3], ColorType::Grayscale, 8)), Err(Error::InvalidImage(_))));
+		assert!(matches!(options.encode(&RawImage::new(&[0; 8], ColorType::Grayscale, 8).stride(1)), Err(Error::InvalidImage(_))));
+		assert!(matches!(options.encode(&RawImage::new(&[0b0011_0000, 0], ColorType::Indexed, 2).palette(&palette)), Err(Error::InvalidImage(_))));
+		assert!(matches!(options.encode(&RawImage::new(&[0; 2], ColorType::Indexed, 1)), Err(Error::UnsupportedOptions(_))));
+		assert!(matches!(options.encode(&RawImage::new(&[0; 4], ColorType::Grayscale, 8).palette(&palette)), Err(Error::UnsupportedOptions(_))));
+		assert!(matches!(options.encode(&RawImage::new(&[0; 16], ColorType::Rgb, 4)), Err(Error::UnsupportedOptions(_))));
+	}
+
+	#[test]
+	fn test_encode_rgba8 () {
+		let pixels = [
+			235, 35, 35, 255,   0, 0, 0, 255,
+			255, 255, 255, 255, 235, 35, 35, 255
+		];
+		let png_data = PngOptions::new().dimensions(2, 2).format(ColorType::Indexed, 2).encode_rgba8(&pixels).unwrap();
+		let image = decode(&png_data).unwrap();
+		assert_eq!((image.header.bit_depth, image.header.color_type), (2, ColorType::Indexed));
+		assert_eq!(chunks(&png_data)[1].data, [0, 0, 0, 235, 35, 35, 255, 255, 255]);
+		assert_eq!(image.pixels, pixels);
+
+		let png_data = PngOptions::new().dimensions(2, 2).format(ColorType::Rgba, 8).encode_rgba8(&pixels).unwrap();
+		assert_eq!(decode(&png_data).unwrap().pixels, pixels);
+
+		assert!(matches!(PngOptions::new().dimensions(2, 2).encode_rgba8(&pixels[4..]), Err(Error::InvalidImage(_))));
+	}
+
+	#[test]
+	fn test_filters_round_trip () {
+		let (width, height) = (37, 23);
+		let pixels = (0..width * height).flat_map(|i| {
+			let (x, y) = (i % width, i / width);
+			[(x * 7) as u8, (y * 11) as u8, (x * y) as u8, (255 - x - y) as u8]
+		}).collect::<Vec<u8>>();
+
+		let options = PngOptions::new().dimensions(width, height).format(ColorType::Rgba, 8);
+
+		let mut sizes = Vec::new();
+		for filter in [Filter::Sub, Filter::Up, Filter::Average, Filter::Paeth, Filter::Adaptive, Filter::BruteForce] {
+			let png_data = options.clone().filter(filter).encode_rgba8(&pixels).unwrap();
+			assert_eq!(decode(&png_data).unwrap().pixels, pixels, "{:?} didn't round trip", filter);
+			sizes.push(png_data.len());
+		}
+
+		assert!(sizes[5] <= *sizes.iter().min().unwrap(), "brute force isn't the smallest");
+	}
+
+	#[test]
+	fn test_compression () {
+		let (width, height) = (37, 23);
+		let pixels = (0..width * height).flat_map(|i| {
+			let (x, y) = (i % width, i / width);
+			[(x * 7) as u8, (y * 11) as u8, (x * y) as u8, 255]
+		}).collect::<Vec<u8>>();
+
+		let options = PngOptions::new().dimensions(width, height).format(ColorType::Rgb, 8);
+		assert_eq!(decode(&options.encode_rgba8(&pixels).unwrap()).unwrap().pixels, pixels);
+
+		// stored blocks are larger than the scanlines they hold
+		let stored = options.clone().compression(Compression::Level(0)).encode_rgba8(&pixels).unwrap();
+		assert_eq!(decode(&stored).unwrap().pixels, pixels);
+		assert!(stored.len() > (height * (1 + width * 3)) as usize);
+
+		let mut sizes = Vec::new();
+		for strategy in [Strategy::Default, Strategy::Filtered, Strategy::Rle, Strategy::HuffmanOnly, Strategy::Stored] {
+			let png_data = options.clone().compression(Compression::Max).strategy(strategy).encode_rgba8(&pixels).unwrap();
+			assert_eq!(decode(&png_data).unwrap().pixels, pixels, "{:?} didn't round trip", strategy);
+			sizes.push(png_data.len());
+		}
+
+		let optimized = options.clone().compression(Compression::Max).optimize(true).encode_rgba8(&pixels).unwrap();
+		assert!(optimized.len() <= *sizes.iter().min().unwrap(), "optimizing isn't the smallest");
+		assert_eq!(decode(&optimized).unwrap().pixels, pixels);
+	}
+
+	#[test]
+	fn test_interlace () {
+		for (width, height, color_type, bit_depth) in [(37, 23, ColorType::Rgba, 8), (13, 9, ColorType::Rgb, 16), (5, 11, ColorType::Grayscale, 1), (3, 3, ColorType::Indexed, 4)] {
+			let pixels = (0..width * height).flat_map(|i| match bit_depth {
+				1 | 4 => [(i % 2 * 255) as u8, (i % 2 * 255) as u8, (i % 2 * 255) as u8, 255],
+				_ => [(i * 7) as u8, (i * 11) as u8, (i * 3) as u8, 255]
+			}).collect::<Vec<u8>>();
+			let options = PngOptions::new().dimensions(width, height).format(color_type, bit_depth);
+
+			for filter in [Filter::None, Filter::Paeth, Filter::Adaptive] {
+				let image = decode(&options.clone().filter(filter).interlace(true).encode_rgba8(&pixels).unwrap()).unwrap();
+				assert!(image.header.interlace, "interlace method isn't adam7");
+				assert_eq!(image.pixels, pixels, "{:?} {:?} didn't round trip", color_type, filter);
+			}
+		}
+	}
+
+	#[test]
+	fn test_text () {
+		let png_data = PngOptions::new()
+			.text(Text::new("Software", "hexpng").unwrap())
+			.text(Text::new("Comment", "#eb2323").unwrap().compressed(true))
+			.text(Text::international("Title", "rot", "de", "Titel").unwrap())
+			.format(ColorType::Rgb, 8)
+			.generate(235, 35, 35, 255)
+			.unwrap();
+		let chunks = chunks(&png_data);
+
+		assert_eq!(chunks.iter().map(|chunk| chunk.chunk_type).collect::<Vec<_>>(), [*b"IHDR", *b"tEXt", *b"zTXt", *b"iTXt", *b"IDAT", *b"IEND"]);
+		assert_eq!(chunks[1].data, b"Software\0hexpng");
+		assert_eq!(chunks[3].data, b"Title\0\0\0de\0Titel\0rot");
+		assert_eq!(decode(&png_data).unwrap().pixels, [235, 35, 35, 255].repeat(100));
+	}
+
+	#[test]
+	fn test_color_space () {
+		let png_data = PngOptions::new().dimensions(100, 100).tag_srgb().cicp(Cicp::SRGB).generate(235, 35, 35, 127).unwrap();
+		let srgb = chunks(&png_data);
+		assert_eq!(srgb.iter().map(|chunk| chunk.chunk_type).collect::<Vec<_>>(), [*b"IHDR", *b"cHRM", *b"gAMA", *b"sRGB", *b"cICP", *b"PLTE", *b"tRNS", *b"IDAT", *b"IEND"]);
+		assert_eq!(srgb[2].data, 45455u32.to_be_bytes());
+		assert_eq!(srgb[3].data, [RenderingIntent::Perceptual as u8]);
+		assert_eq!(decode(&png_data).unwrap().pixels, [235, 35, 35, 127].repeat(100 * 100));
+
+		let profile = (0..=255).collect::<Vec<u8>>();
+		let png_data = PngOptions::new().icc_profile(IccProfile::new("test profile", &profile).unwrap()).generate(235, 35, 35, 255).unwrap();
+		let iccp = chunks(&png_data).into_iter().find(|chunk| &chunk.chunk_type == b"iCCP").unwrap().data;
+		assert_eq!(iccp[..14], *b"test profile\0\0");
+		assert_eq!(unzlib(&iccp[14..]).unwrap(), profile);
+
+		assert!(matches!(
+			PngOptions::new().tag_srgb().icc_profile(IccProfile::new("test profile", &profile).unwrap()).generate(0, 0, 0, 255),
+			Err(Error::UnsupportedOptions(_))
+		));
+		for gamma in [0.0, -1.0, f64::NAN] {
+			assert!(matches!(PngOptions::new().gamma(gamma).generate(0, 0, 0, 255), Err(Error::InvalidChunk(_))));
+		}
+	}
+
+	#[test]
+	fn test_physical_metadata () {
+		let png_data = PngOptions::new()
+			.resolution(Resolution::dpi(300.0))
+			.modified(Timestamp::new(2024, 5, 17, 12, 30, 0).unwrap())
+			.format(ColorType::Rgb, 8)
+			.generate(235, 35, 35, 255)
+			.unwrap();
+		let chunks = chunks(&png_data);
+
+		assert_eq!(chunks.iter().map(|chunk| chunk.chunk_type).collect::<Vec<_>>(), [*b"IHDR", *b"pHYs", *b"tIME", *b"IDAT", *b"IEND"]);
+		assert_eq!(chunks[1].data, [0, 0, 0x2e, 0x23, 0, 0, 0x2e, 0x23, 1]);
+		assert_eq!(chunks[2].data, [0x07, 0xe8, 5, 17, 12, 30, 0]);
+
+		assert!(matches!(PngOptions::new().resolution(Resolution::per_meter(0, 0)).generate(0, 0, 0, 255), Err(Error::InvalidChunk(_))));
+	}
+
+	#[test]
+	fn test_idat_size () {
+		// noise doesn't compress, so it takes a few chunks
+		let mut state = 1u32;
+		let pixels = (0..100 * 100 * 4).map(|_| {
+			state = state.wrapping_mul(1664525).wrapping_add(1013904223);
+			(state >> 24) as u8
+		}).collect::<Vec<u8>>();
+		let options = PngOptions::new().dimensions(100, 100);
+
+		let png_data = options.encode_rgba8(&pixels).unwrap();
+		let idats = chunks(&png_data).iter().filter(|chunk| &chunk.chunk_type == b"IDAT").map(|chunk| chunk.data.len()).collect::<Vec<_>>();
+		assert_eq!(idats.len(), 5);
+		assert!(idats[..4].iter().all(|&len| len == 8192));
+		assert_eq!(decode(&png_data).unwrap().pixels, pixels);
+
+		let png_data = options.clone().idat_size(10).generate(235, 35, 35, 255).unwrap();
+		assert!(chunks(&png_data).iter().filter(|chunk| &chunk.chunk_type == b"IDAT").count() > 1);
+		assert_eq!(decode(&png_data).unwrap().pixels, [235, 35, 35, 255].repeat(100 * 100));
+
+		// animation frames are split too, the fdAT chunks numbered in order
+		let animation = Animation::new().frame(Frame::new(pixels.clone(), 100, 100)).frame(Frame::new(pixels, 100, 100));
+		let png_data = options.encode_animation(&animation).unwrap();
+		let sequence = chunks(&png_data).iter()
+			.filter(|chunk| &chunk.chunk_type == b"fcTL" || &chunk.chunk_type == b"fdAT")
+			.map(|chunk| u32::from_be_bytes(chunk.data[..4].try_into().unwrap()))
+			.collect::<Vec<_>>();
+		assert_eq!(sequence, (0..7).collect::<Vec<_>>());
+
+		assert!(matches!(options.clone().idat_size(0).generate(0, 0, 0, 0), Err(Error::UnsupportedOptions(_))));
+		assert!(matches!(options.idat_size(u32::MAX).generate(0, 0, 0, 0), Err(Error::UnsupportedOptions(_))));
+	}
+
+	#[test]
+	fn test_animation () {
+		let (red, blue) = ([235, 35, 35, 255].repeat(4), [35, 35, 235, 255].repeat(2));
+		let animation = Animation::new()
+			.frame(Frame::new(red.clone(), 2, 2).delay(1, 2))
+			.frame(Frame::new(blue.clone(), 1, 2).offset(1, 0).dispose(DisposeOp::Background).blend(BlendOp::Over))
+			.plays(3);
+		let png_data = PngOptions::new().dimensions(2, 2).format(ColorType::Rgb, 8).encode_animation(&animation).unwrap();
+		let chunks = chunks(&png_data);
+
+		assert_eq!(chunks.iter().map(|chunk| chunk.chunk_type).collect::<Vec<_>>(), [*b"IHDR", *b"acTL", *b"fcTL", *b"IDAT", *b"fcTL", *b"fdAT", *b"IEND"]);
+		assert_eq!(chunks[1].data, [0, 0, 0, 2, 0, 0, 0, 3]);
+		assert_eq!(chunks[2].data, [0, 0, 0, 0, 0, 0, 0, 2, 0, 0, 0, 2, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1, 0, 2, 0, 0]);
+		assert_eq!(chunks[4].data, [0, 0, 0, 1, 0, 0, 0, 1, 0, 0, 0, 2, 0, 0, 0, 1, 0, 0, 0, 0, 0, 1, 0, 10, 1, 1]);
+		assert_eq!(decode(&png_data).unwrap().pixels, red);
+
+		// fdAT is a sequence number followed by what would be IDAT data
+		assert_eq!(chunks[5].data[..4], [0, 0, 0, 2]);
+		assert_eq!(frame(&png_data, &chunks[5], 1, 2), blue);
+
+		assert!(matches!(PngOptions::new().encode_animation(&Animation::new()), Err(Error::InvalidImage(_))));
+		let offset = Animation::new().frame(Frame::new([0; 4].repeat(4), 2, 2).offset(1, 0));
+		assert!(matches!(PngOptions::new().dimensions(3, 3).encode_animation(&offset), Err(Error::InvalidImage(_))));
+	}
+
+	#[test]
+	fn test_transition () {
+		let colors = [Color::rgb(255, 0, 0), Color::rgb(0, 0, 255), Color::rgb(255, 0, 0)];
+		let png_data = PngOptions::new().dimensions(4, 4).format(ColorType::Rgb, 8).transition(&colors, 5, (1, 25)).unwrap();
+		let chunks = chunks(&png_data);
+
+		assert_eq!((chunks[1].chunk_type, chunks[1].data), (*b"acTL", &[0, 0, 0, 5, 0, 0, 0, 0][..]));
+		assert_eq!(decode(&png_data).unwrap().pixels, [255, 0, 0, 255].repeat(16));
+
+		// fcTL and fdAT share one sequence
+		let sequence = chunks.iter()
+			.filter(|chunk| &chunk.chunk_type == b"fcTL" || &chunk.chunk_type == b"fdAT")
+			.map(|chunk| u32::from_be_bytes(chunk.data[..4].try_into().unwrap()))
+			.collect::<Vec<u32>>();
+		assert_eq!(sequence, (0..9).collect::<Vec<u32>>());
+
+		let middle = chunks.iter().filter(|chunk| &chunk.chunk_type == b"fdAT").nth(1).unwrap();
+		assert_eq!(frame(&png_data, middle, 4, 4), [0, 0, 255, 255].repeat(16));
+
+		// the smallest format fits every frame
+		let png_data = PngOptions::new().transition(&[Color::rgb(0, 0, 0), Color::rgb(255, 255, 255)], 3, (1, 10)).unwrap();
+		let image = decode(&png_data).unwrap();
+		assert_eq!(image.header.color_type, ColorType::Grayscale);
+		assert_eq!(image.pixels, [0, 0, 0, 255].repeat(100));
+	}
+
+	#[test]
+	fn test_gradient () {
+		let gradient = Gradient::linear(90.0).stop(0.0, Color::rgb(0, 0, 0)).stop(1.0, Color::rgb(255, 255, 255));
+		let image = decode(&PngOptions::new().dimensions(4, 2).gradient(&gradient).unwrap()).unwrap();
+
+		// a horizontal gray ramp needs nothing more than grayscale
+		assert_eq!(image.header.color_type, ColorType::Grayscale);
+		assert_eq!(image.pixels, [32, 96, 159, 223].repeat(2).into_iter().flat_map(|level| [level, level, level, 255]).collect::<Vec<u8>>());
+
+		assert!(matches!(PngOptions::new().width(0).gradient(&gradient), Err(Error::InvalidDimensions { .. })));
+	}
+
+	#[test]
+	fn test_pattern () {
+		let checkerboard = Pattern::checkerboard(1, Color::rgb(0, 0, 0), Color::rgb(255, 255, 255));
+		let image = decode(&PngOptions::new().dimensions(8, 2).pattern(&checkerboard).unwrap()).unwrap();
+		assert_eq!((image.header.bit_depth, image.header.color_type), (1, ColorType::Grayscale));
+		assert_eq!(image.pixels, (0..16).flat_map(|i| {
+			let level = if (i + i / 8) % 2 == 1 { 255 } else { 0 };
+			[level, level, level, 255]
+		}).collect::<Vec<u8>>());
+
+		let png_data = PngOptions::new().dimensions(16, 16).generate_preview(235, 35, 35, 128).unwrap();
+		assert!(decode(&png_data).unwrap().pixels.chunks(4).all(|pixel| pixel[3] == 255), "preview isn't opaque");
+		assert!(matches!(PngOptions::new().width(0).generate_preview(0, 0, 0, 0), Err(Error::InvalidDimensions { .. })));
+	}
+
+	#[test]
+	fn test_palette () {
+		let colors = [Color::rgb(235, 35, 35), Color::rgb(35, 235, 35), Color::rgb(35, 35, 235)];
+		let palette = Palette::new(&colors).swatch_size(2, 2).gutter(1, Color::rgb(255, 255, 255));
+		let png_data = PngOptions::new().palette(&palette).unwrap();
+		let image = decode(&png_data).unwrap();
+
+		let (red, green, blue, white) = ([235, 35, 35, 255], [35, 235, 35, 255], [35, 35, 235, 255], [255; 4]);
+		let row = [red, red, white, green, green, white, blue, blue].concat();
+		assert_eq!((image.header.width, image.header.height), (8, 2));
+		assert_eq!((image.header.bit_depth, image.header.color_type), (2, ColorType::Indexed));
+		assert_eq!(chunks(&png_data)[1].data.len(), 4 * 3);
+		assert_eq!(image.pixels, row.repeat(2));
+
+		let image = decode(&PngOptions::new().format(ColorType::Rgb, 8).palette(&palette).unwrap()).unwrap();
+		assert_eq!((image.header.bit_depth, image.header.color_type), (8, ColorType::Rgb));
+		assert_eq!(image.pixels, row.repeat(2));
+
+		// too many colors for a palette
+		let colors = (0..300).map(|i| Color::rgb(i as u8, (i / 256) as u8, 0)).collect::<Vec<_>>();
+		let image = decode(&PngOptions::new().palette(&Palette::new(&colors).swatch_size(1, 1)).unwrap()).unwrap();
+		assert_eq!((image.header.bit_depth, image.header.color_type), (8, ColorType::Rgb));
+		assert_eq!(image.pixels[..8], [0, 0, 0, 255, 1, 0, 0, 255]);
+	}
+
+	#[test]
+	fn test_identicon () {
+		let options = PngOptions::new().dimensions(60, 60);
+		let png_data = options.identicon(&Identicon::new("user-1234")).unwrap();
+		let image = decode(&png_data).unwrap();
+		assert_eq!((image.header.width, image.header.height), (60, 60));
+		assert_eq!(image.pixels.len(), 60 * 60 * 4);
+		assert_eq!(png_data, options.identicon(&Identicon::new(b"user-1234")).unwrap());
+		assert_ne!(png_data, options.identicon(&Identicon::new("user-1235")).unwrap());
+	}
+
+	#[test]
+	fn test_label () {
+		let options = PngOptions::new().dimensions(64, 16);
+
+		// black #000000 on white, one bit per pixel
+		let png_data = options.clone().label(Label::hex(Color::rgb(0, 0, 0))).generate(255, 255, 255, 255).unwrap();
+		let image = decode(&png_data).unwrap();
+		assert_eq!((image.header.bit_depth, image.header.color_type), (1, ColorType::Grayscale));
+		assert!(image.pixels.chunks(4).any(|pixel| pixel == [0, 0, 0, 255]), "label isn't drawn");
+		assert_ne!(png_data, options.generate(255, 255, 255, 255).unwrap());
+
+		// a translucent label color is blended with the swatch and its
+		// black label, which the palette's colors don't account for
+		let label = Label::new("ab").unwrap().color(Color::new(0, 0, 0, 128));
+		let palette = Palette::new(&[Color::rgb(255, 255, 255)]).labels(true);
+		let image = decode(&options.label(label).palette(&palette).unwrap()).unwrap();
+		assert_eq!((image.header.bit_depth, image.header.color_type), (2, ColorType::Indexed));
+	}
+
+	#[test]
+	fn test_smallest_format () {
+		let png_data = PngOptions::new().generate(255, 255, 255, 255).unwrap();
+		let image = decode(&png_data).unwrap();
+		assert_eq!((image.header.bit_depth, image.header.color_type), (1, ColorType::Grayscale));
+		assert_eq!(chunks(&png_data).iter().map(|chunk| chunk.chunk_type).collect::<Vec<_>>(), [*b"IHDR", *b"IDAT", *b"IEND"]);
+		assert_eq!(image.pixels, [255; 4].repeat(100));
+
+		let options = PngOptions::new().dimensions(100, 100);
+		let png_data = options.generate(235, 35, 35, 127).unwrap();
+		let image = decode(&png_data).unwrap();
+		assert_eq!((image.header.bit_depth, image.header.color_type), (1, ColorType::Indexed));
+		assert_eq!(chunks(&png_data).iter().map(|chunk| chunk.chunk_type).collect::<Vec<_>>(), [*b"IHDR", *b"PLTE", *b"tRNS", *b"IDAT", *b"IEND"]);
+		assert_eq!(image.pixels, [235, 35, 35, 127].repeat(100 * 100));
+
+		for (r, g, b, a) in [(235, 35, 35, 127), (235, 35, 35, 255), (17, 17, 17, 255)] {
+			let smallest = PngOptions::new().generate(r, g, b, a).unwrap();
+			let rgba = PngOptions::new().format(ColorType::Rgba, 8).generate(r, g, b, a).unwrap();
+			assert!(smallest.len() <= rgba.len(), "picked format is larger than rgba");
+			assert_eq!(decode(&smallest).unwrap().pixels, decode(&rgba).unwrap().pixels);
+		}
+	}
+
+	#[test]
+	fn test_requested_format () {
+		let image = decode(&PngOptions::new().format(ColorType::Rgb, 16).generate(235, 35, 35, 255).unwrap()).unwrap();
+		assert_eq!((image.header.bit_depth, image.header.color_type), (16, ColorType::Rgb));
+		assert_eq!(image.pixels, [235, 35, 35, 255].repeat(100));
+
+		assert!(matches!(PngOptions::new().format(ColorType::Rgb, 16).generate(235, 35, 35, 127), Err(Error::UnsupportedOptions(_))));
+		assert!(matches!(PngOptions::new().format(ColorType::Indexed, 16).generate(235, 35, 35, 255), Err(Error::UnsupportedOptions(_))));
+	}
+
+	#[test]
+	fn test_invalid_dimensions () {
+		assert_eq!(PngOptions::new().width(0).generate(0, 0, 0, 0), Err(Error::InvalidDimensions { width: 0, height: 10 }));
+		assert_eq!(PngOptions::new().height(0).generate(0, 0, 0, 0), Err(Error::InvalidDimensions { width: 10, height: 0 }));
+		assert_eq!(
+			PngOptions::new().width(MAX_DIMENSION + 1).generate(0, 0, 0, 0),
+			Err(Error::InvalidDimensions { width: MAX_DIMENSION + 1, height: 10 })
+		);
+		assert_eq!(PngOptions::new().dimensions(MAX_DIMENSION, MAX_DIMENSION).generate(0, 0, 0, 0), Err(Error::ImageTooLarge));
+	}
+
+}
@@ -0,0 +1,208 @@
+// textual metadata, stored in tEXt, zTXt and iTXt chunks
+
+use crate::Error;
+
+#[cfg(not(feature = "std"))]
+use alloc::{ string::String, vec::Vec };
+
+
+
+/// a keyword and text pair embedded in the png
+///
+/// the png spec predefines keywords like `Title`, `Author`, `Description`,
+/// `Copyright`, `Creation Time`, `Software`, `Source` and `Comment`, but
+/// any 1-79 printable latin-1 characters without leading, trailing or
+/// consecutive spaces are allowed
+///
+/// ```
+/// use hexpng::{ PngOptions, Text };
+///
+/// let png = PngOptions::new()
+///     .text(Text::new("Software", "hexpng").unwrap())
+///     .text(Text::new("Comment", "#eb2323").unwrap().compressed(true))
+///     .text(Text::international("Title", "rot", "de", "Titel").unwrap())
+///     .generate(235, 35, 35, 255)
+///     .unwrap();
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Text {
+	keyword: Vec<u8>,
+	kind: Kind,
+	compressed: bool
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+enum Kind {
+	// latin-1 text of a tEXt or zTXt chunk
+	Latin1(Vec<u8>),
+	// utf-8 text of an iTXt chunk
+	International { text: String, language: String, translated_keyword: String }
+}
+
+impl Text {
+
+	/// latin-1 text, stored in a tEXt chunk or a zTXt chunk if it's
+	/// [compressed](Text::compressed)
+	///
+	/// fails with [`Error::InvalidChunk`] if the keyword isn't valid or
+	/// the text has characters that aren't in latin-1 or nul characters
+	pub fn new (keyword: &str, text: &str) -> Result<Self, Error> {
+		let text = latin1(text).filter(|text| !text.contains(&0)).ok_or(Error::InvalidChunk("text isn't latin-1 without nul characters"))?;
+
+		Ok(Self {
+			keyword: self::keyword(keyword)?,
+			kind: Kind::Latin1(text),
+			compressed: false
+		})
+	}
+
+	/// utf-8 text, stored in an iTXt chunk
+	///
+	/// `language` is a tag like `en` or `pt-BR` for the language of the
+	/// text, and `translated_keyword` is the keyword translated to it,
+	/// both may be empty when they're unknown
+	pub fn international (keyword: &str, text: &str, language: &str, translated_keyword: &str) -> Result<Self, Error> {
+		let valid_language = language.is_empty() || language.split('-').all(|word| {
+			(1..=8).contains(&word.len()) && word.bytes().all(|byte| byte.is_ascii_alphanumeric())
+		});
+		if !valid_language {
+			return Err(Error::InvalidChunk("language tag isn't hyphen separated words of 1-8 letters or digits"));
+		}
+		if translated_keyword.contains('\0') {
+			return Err(Error::InvalidChunk("translated keyword has nul characters"));
+		}
+
+		Ok(Self {
+			keyword: self::keyword(keyword)?,
+			kind: Kind::International {
+				text: text.into(),
+				language: language.into(),
+				translated_keyword: translated_keyword.into()
+			},
+			compressed: false
+		})
+	}
+
+	/// zlib compress the text, worth it for long text only
+	pub fn compressed (mut self, compressed: bool) -> Self {
+		self.compressed = compressed;
+		self
+	}
+
+	// the type of chunk the text is stored in
+	pub(crate) fn chunk_type (&self) -> &'static [u8; 4] {
+		match (&self.kind, self.compressed) {
+			(Kind::Latin1(_), false) => b"tEXt",
+			(Kind::Latin1(_), true) => b"zTXt",
+			(Kind::International { .. }, _) => b"iTXt"
+		}
+	}
+
+	pub(crate) fn chunk_data (&self) -> Result<Vec<u8>, Error> {
+		let mut data = self.keyword.clone();
+		data.push(0);
+
+		match &self.kind {
+			Kind::Latin1(text) if self.compressed => {
+				data.push(0); // compression method
+				data.extend(crate::deflate::zlib(text));
+			},
+			Kind::Latin1(text) => data.extend_from_slice(text),
+			Kind::International { text, language, translated_keyword } => {
+				data.extend([self.compressed as u8, 0]); // compression flag and method
+				data.extend_from_slice(language.as_bytes());
+				data.push(0);
+				data.extend_from_slice(translated_keyword.as_bytes());
+				data.push(0);
+				if self.compressed {
+					data.extend(crate::deflate::zlib(text.as_bytes()));
+				} else {
+					data.extend_from_slice(text.as_bytes());
+				}
+			}
+		}
+
+		Ok(data)
+	}
+
+}
+
+
+
+// latin-1 bytes of a keyword, fails if it isn't 1-79 printable characters
+// without leading, trailing or consecutive spaces
+pub(crate) fn keyword (keyword: &str) -> Result<Vec<u8>, Error> {
+	let bytes = latin1(keyword).ok_or(Error::InvalidChunk("keyword isn't latin-1"))?;
+
+	if bytes.is_empty() || bytes.len() > 79 {
+		return Err(Error::InvalidChunk("keyword isn't 1-79 characters long"));
+	}
+	if !bytes.iter().all(|&byte| matches!(byte, 32..=126 | 161..=255)) {
+		return Err(Error::InvalidChunk("keyword has non printable characters"));
+	}
+	if bytes.starts_with(b" ") || bytes.ends_with(b" ") || bytes.windows(2).any(|pair| pair == b"  ") {
+		return Err(Error::InvalidChunk("keyword has leading, trailing or consecutive spaces"));
+	}
+
+	Ok(bytes)
+}
+
+// `text` as latin-1 bytes, if every character is in latin-1
+fn latin1 (text: &str) -> Option<Vec<u8>> {
+	text.chars().map(|c| u8::try_from(c).ok()).collect()
+}
+
+
+
+#[cfg(test)]
+mod tests {
+
+	use super::{ Text, keyword };
+	use crate::{ Error, deflate::unzlib };
+
+
+
+	#[test]
+	fn test_keyword () {
+		assert_eq!(keyword("Creation Time"), Ok(b"Creation Time".to_vec()));
+		assert_eq!(keyword("Grüße"), Ok(b"Gr\xfc\xdfe".to_vec()));
+		assert_eq!(keyword(&"k".repeat(79)).map(|k| k.len()), Ok(79));
+
+		for invalid in ["", " Title", "Title ", "Creation  Time", "Tab\there", "\u{a0}nbsp", "Ω", &"k".repeat(80)] {
+			assert!(matches!(keyword(invalid), Err(Error::InvalidChunk(_))), "{:?} is a valid keyword", invalid);
+		}
+	}
+
+	#[test]
+	fn test_chunk_data () {
+		let text = Text::new("Comment", "caf\u{e9}").unwrap();
+		assert_eq!(text.chunk_type(), b"tEXt");
+		assert_eq!(text.chunk_data().unwrap(), b"Comment\0caf\xe9");
+
+		let text = text.compressed(true);
+		assert_eq!(text.chunk_type(), b"zTXt");
+		let data = text.chunk_data().unwrap();
+		assert_eq!(data[..9], *b"Comment\0\0");
+		assert_eq!(unzlib(&data[9..]).unwrap(), b"caf\xe9");
+
+		let text = Text::international("Title", "\u{043a}\u{0440}\u{0430}\u{0441}\u{043d}\u{044b}\u{0439}", "ru", "\u{0417}\u{0430}\u{0433}\u{043e}\u{043b}\u{043e}\u{0432}\u{043e}\u{043a}").unwrap();
+		assert_eq!(text.chunk_type(), b"iTXt");
+		assert_eq!(text.chunk_data().unwrap(), "Title\0\0\0ru\0\u{0417}\u{0430}\u{0433}\u{043e}\u{043b}\u{043e}\u{0432}\u{043e}\u{043a}\0\u{043a}\u{0440}\u{0430}\u{0441}\u{043d}\u{044b}\u{0439}".as_bytes());
+
+		let data = Text::international("Title", "red", "", "").unwrap().compressed(true).chunk_data().unwrap();
+		assert_eq!(data[..10], *b"Title\0\x01\0\0\0");
+		assert_eq!(unzlib(&data[10..]).unwrap(), b"red");
+	}
+
+	#[test]
+	fn test_invalid_text () {
+		assert!(matches!(Text::new("Comment", "\u{3b1}"), Err(Error::InvalidChunk(_))));
+		assert!(matches!(Text::new("Comment", "a\0b"), Err(Error::InvalidChunk(_))));
+		assert!(matches!(Text::new("", "text"), Err(Error::InvalidChunk(_))));
+		assert!(matches!(Text::international("Title", "text", "en_US", ""), Err(Error::InvalidChunk(_))));
+		assert!(matches!(Text::international("Title", "text", "toolonglanguage", ""), Err(Error::InvalidChunk(_))));
+		assert!(matches!(Text::international("Title", "text", "en", "a\0b"), Err(Error::InvalidChunk(_))));
+		assert!(Text::international("Title", "text", "pt-BR", "T\u{ed}tulo").is_ok());
+	}
+
+}
@@ -0,0 +1,587 @@
+// checking pngs from other encoders, reporting everything that's wrong
+// with them instead of stopping at the first problem like the decoder
+
+use core::fmt;
+
+use crate::{ ColorType, Error, Header };
+use crate::{ decode, png };
+
+#[cfg(not(feature = "std"))]
+use alloc::vec::Vec;
+
+
+
+const IHDR: [u8; 4] = *b"IHDR";
+const PLTE: [u8; 4] = *b"PLTE";
+const IDAT: [u8; 4] = *b"IDAT";
+const IEND: [u8; 4] = *b"IEND";
+
+// chunks that can only appear once
+pub(crate) const UNIQUE: [&[u8; 4]; 15] = [b"IHDR", b"PLTE", b"IEND", b"cHRM", b"gAMA", b"iCCP", b"sBIT", b"sRGB", b"cICP", b"bKGD", b"hIST", b"tRNS", b"pHYs", b"tIME", b"acTL"];
+// chunks that have to come before PLTE, and so before the image data
+pub(crate) const BEFORE_PLTE: [&[u8; 4]; 6] = [b"cHRM", b"gAMA", b"iCCP", b"sBIT", b"sRGB", b"cICP"];
+// chunks that have to come after the PLTE of indexed images, but before the image data
+const AFTER_PLTE: [&[u8; 4]; 3] = [b"bKGD", b"hIST", b"tRNS"];
+// the other chunks that have to come before the image data
+const BEFORE_IDAT: [&[u8; 4]; 4] = [b"PLTE", b"pHYs", b"sPLT", b"acTL"];
+
+
+
+/// how serious a [`Diagnostic`] is
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Severity {
+	/// the png isn't quite right, but decoders can still show it
+	Warning,
+	/// the png breaks the spec, decoders may reject it or show it wrong
+	Error
+}
+
+impl fmt::Display for Severity {
+	fn fmt (&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			Self::Warning => write!(f, "warning"),
+			Self::Error => write!(f, "error")
+		}
+	}
+}
+
+/// something wrong with a png, found by [`validate`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Problem {
+	/// the file doesn't start with the png signature
+	BadSignature,
+	/// a chunk is cut short by the end of the file
+	TruncatedChunk,
+	/// a chunk's length is over 2^31-1 bytes
+	ChunkTooLong(u32),
+	/// a chunk's type isn't 4 ascii letters
+	InvalidChunkType([u8; 4]),
+	/// the crc stored after a chunk doesn't match its type and data
+	CrcMismatch { chunk_type: [u8; 4], stored: u32, computed: u32 },
+	/// a critical chunk hexpng doesn't know, which decoders can't skip
+	UnknownCriticalChunk([u8; 4]),
+	/// more than one of a chunk that can only appear once
+	DuplicateChunk([u8; 4]),
+	/// a chunk somewhere the png spec doesn't allow it
+	MisplacedChunk { chunk_type: [u8; 4], reason: &'static str },
+	/// a chunk the png needs isn't there
+	MissingChunk([u8; 4]),
+	/// the IHDR chunk is malformed or has an invalid combination of
+	/// fields, like 16 bit indexed color
+	InvalidHeader(&'static str),
+	/// a chunk's data isn't valid for the png
+	InvalidChunk { chunk_type: [u8; 4], reason: &'static str },
+	/// the image data doesn't decompress or doesn't fit the image
+	InvalidImageData(&'static str),
+	/// this many bytes come after the IEND chunk
+	TrailingData(usize)
+}
+
+impl Problem {
+
+	/// how serious the problem is, misplaced and repeated ancillary
+	/// chunks and trailing data are warnings, everything else an error
+	pub fn severity (&self) -> Severity {
+		match self {
+			Self::TrailingData(_) => Severity::Warning,
+			Self::DuplicateChunk(chunk_type) | Self::MisplacedChunk { chunk_type, .. } if chunk_type[0].is_ascii_lowercase() => Severity::Warning,
+			_ => Severity::Error
+		}
+	}
+
+}
+
+impl fmt::Display for Problem {
+	fn fmt (&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			Self::BadSignature => write!(f, "not a png, the signature is missing or corrupted"),
+			Self::TruncatedChunk => write!(f, "a chunk is cut short by the end of the file"),
+			Self::ChunkTooLong(length) => write!(f, "chunk length {} is over 2^31-1", length),
+			Self::InvalidChunkType(chunk_type) => write!(f, "invalid chunk type \"{}\"", chunk_type.escape_ascii()),
+			Self::CrcMismatch { chunk_type, stored, computed } => {
+				write!(f, "CRC error in chunk {} (computed {:08x}, expected {:08x})", chunk_type.escape_ascii(), computed, stored)
+			},
+			Self::UnknownCriticalChunk(chunk_type) => write!(f, "unknown critical chunk {}", chunk_type.escape_ascii()),
+			Self::DuplicateChunk(chunk_type) => write!(f, "multiple {} chunks", chunk_type.escape_ascii()),
+			Self::MisplacedChunk { chunk_type, reason } => write!(f, "{} chunk {}", chunk_type.escape_ascii(), reason),
+			Self::MissingChunk(chunk_type) => write!(f, "no {} chunk", chunk_type.escape_ascii()),
+			Self::InvalidHeader(reason) => write!(f, "invalid IHDR: {}", reason),
+			Self::InvalidChunk { chunk_type, reason } => write!(f, "invalid {} chunk: {}", chunk_type.escape_ascii(), reason),
+			Self::InvalidImageData(reason) => write!(f, "invalid image data: {}", reason),
+			Self::TrailingData(len) => write!(f, "{} bytes of trailing data after IEND", len)
+		}
+	}
+}
+
+/// a problem and where in the png it was found
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Diagnostic {
+	/// the byte offset of the chunk with the problem, or of where a
+	/// missing chunk should be
+	pub offset: usize,
+	/// what's wrong
+	pub problem: Problem
+}
+
+impl fmt::Display for Diagnostic {
+	fn fmt (&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		write!(f, "{} at byte {}: {}", self.problem.severity(), self.offset, self.problem)
+	}
+}
+
+
+
+/// everything [`validate`] found out about a png
+///
+/// displays like pngcheck, a line per diagnostic then a summary
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Report {
+	/// the png's header, if it has a valid IHDR chunk
+	pub header: Option<Header>,
+	/// how many chunks were read
+	pub chunks: usize,
+	/// size of the png in bytes
+	pub len: usize,
+	/// every problem in the order it was found
+	pub diagnostics: Vec<Diagnostic>
+}
+
+impl Report {
+
+	/// whether there are no errors, warnings are fine
+	pub fn is_valid (&self) -> bool {
+		self.count(Severity::Error) == 0
+	}
+
+	/// how many diagnostics have `severity`
+	pub fn count (&self, severity: Severity) -> usize {
+		self.diagnostics.iter().filter(|diagnostic| diagnostic.problem.severity() == severity).count()
+	}
+
+	fn push (&mut self, offset: usize, problem: Problem) {
+		self.diagnostics.push(Diagnostic { offset, problem });
+	}
+
+}
+
+impl fmt::Display for Report {
+	fn fmt (&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		for diagnostic in &self.diagnostics {
+			writeln!(f, "{}", diagnostic)?;
+		}
+
+		let (errors, warnings) = (self.count(Severity::Error), self.count(Severity::Warning));
+		let plural = |count: usize| if count == 1 { "" } else { "s" };
+		match (self.header, errors) {
+			(Some(header), 0) => {
+				let bits_per_pixel = header.color_type.bits_per_pixel(header.bit_depth);
+				let raw = header.width as f64 * header.height as f64 * bits_per_pixel as f64 / 8.0;
+				let color_type = match header.color_type {
+					ColorType::Grayscale => "grayscale",
+					ColorType::GrayscaleAlpha => "grayscale+alpha",
+					ColorType::Rgb => "RGB",
+					ColorType::Rgba => "RGB+alpha",
+					ColorType::Indexed => "palette"
+				};
+
+				write!(
+					f, "OK: {}x{}, {}-bit {}, {}, {} chunks, {:.1}% compression",
+					header.width, header.height, bits_per_pixel, color_type,
+					if header.interlace { "interlaced" } else { "non-interlaced" },
+					self.chunks, 100.0 * (1.0 - self.len as f64 / raw)
+				)?;
+				if warnings > 0 {
+					write!(f, ", {} warning{}", warnings, plural(warnings))?;
+				}
+				Ok(())
+			},
+			_ => write!(f, "ERRORS DETECTED: {} error{}, {} warning{}", errors, plural(errors), warnings, plural(warnings))
+		}
+	}
+}
+
+
+
+/// check a png against the spec, reporting every problem found instead
+/// of failing on the first like [`decode`](crate::decode)
+///
+/// every chunk's length, type and crc are checked, critical chunks have
+/// to be known and in order, the IHDR fields have to be a valid
+/// combination, and the image data has to decompress and fit the image
+///
+/// ```
+/// use hexpng::{ PngOptions, validate };
+///
+/// let mut png = PngOptions::new().generate(235, 35, 35, 255).unwrap();
+/// assert!(validate(&png).is_valid());
+///
+/// // corrupt the IHDR chunk's crc
+/// png[29] ^= 0xff;
+/// let report = validate(&png);
+/// assert!(!report.is_valid());
+/// assert!(report.to_string().starts_with("error at byte 8: CRC error in chunk IHDR"));
+/// ```
+///
+/// the image data is decompressed however large the IHDR chunk says the
+/// image is, use [`validate_limited`] for pngs from untrusted sources
+pub fn validate (png: &[u8]) -> Report {
+	validate_limited(png, usize::MAX)
+}
+
+/// [`validate`], but the image data is only checked if it decompresses
+/// to at most `max_bytes`, otherwise the report says the image is too
+/// large to check
+///
+/// a few megabytes of deflate data can claim gigabytes of pixels, so
+/// servers checking uploaded pngs should always set a limit
+///
+/// ```
+/// use hexpng::{ PngOptions, Problem, validate_limited };
+///
+/// let png = PngOptions::new().dimensions(100, 100).generate(235, 35, 35, 255).unwrap();
+/// assert!(validate_limited(&png, 1 << 20).is_valid());
+/// assert_eq!(validate_limited(&png, 1000).diagnostics[0].problem, Problem::InvalidImageData("the image is too large to check"));
+/// ```
+pub fn validate_limited (png: &[u8], max_bytes: usize) -> Report {
+	let mut report = Report { header: None, chunks: 0, len: png.len(), diagnostics: Vec::new() };
+	let Some(mut rest) = png.strip_prefix(png::HEADER) else {
+		report.push(0, Problem::BadSignature);
+		return report;
+	};
+
+	let mut offset = png::HEADER.len();
+	let mut seen = Vec::<[u8; 4]>::new();
+	let (mut palette, mut transparency, mut idat, mut idat_offset) = (None, None, Vec::new(), None);
+
+	while !rest.is_empty() {
+		let (Some(length), Some(chunk_type)) = (rest.first_chunk::<4>(), rest.get(4..8)) else {
+			report.push(offset, Problem::TruncatedChunk);
+			break;
+		};
+		let (length, chunk_type) = (u32::from_be_bytes(*length), [chunk_type[0], chunk_type[1], chunk_type[2], chunk_type[3]]);
+		if length > png::MAX_CHUNK_LEN {
+			report.push(offset, Problem::ChunkTooLong(length));
+			break;
+		}
+		if !chunk_type.iter().all(u8::is_ascii_alphabetic) {
+			report.push(offset, Problem::InvalidChunkType(chunk_type));
+			break;
+		}
+		let Some((data, stored)) = rest.get(8..8 + length as usize).zip(rest.get(8 + length as usize..).and_then(|rest| rest.first_chunk::<4>())) else {
+			report.push(offset, Problem::TruncatedChunk);
+			break;
+		};
+
+		let (stored, computed) = (u32::from_be_bytes(*stored), png::chunk_checksum(&chunk_type, data));
+		if stored != computed {
+			report.push(offset, Problem::CrcMismatch { chunk_type, stored, computed });
+		}
+		report.chunks += 1;
+
+		check_order(&mut report, offset, chunk_type, &seen);
+		match &chunk_type {
+			b"IHDR" if !seen.contains(&IHDR) => {
+				report.header = Header::parse(data).map_err(|e| report.push(offset, Problem::InvalidHeader(match e {
+					Error::InvalidPng(reason) => reason,
+					_ => "width and height have to be 1 to 2^31-1"
+				}))).ok();
+			},
+			b"PLTE" => {
+				check_palette(&mut report, offset, data);
+				palette = Some(data);
+			},
+			b"tRNS" => {
+				check_transparency(&mut report, offset, data, palette);
+				transparency = Some(data);
+			},
+			b"IDAT" => {
+				idat.extend_from_slice(data);
+				idat_offset.get_or_insert(offset);
+			},
+			b"IEND" if !data.is_empty() => report.push(offset, Problem::InvalidChunk { chunk_type, reason: "has to be empty" }),
+			b"IHDR" | b"IEND" => {},
+			_ if chunk_type[0].is_ascii_uppercase() => report.push(offset, Problem::UnknownCriticalChunk(chunk_type)),
+			_ => {}
+		}
+
+		seen.push(chunk_type);
+		offset += 12 + length as usize;
+		rest = &rest[12 + length as usize..];
+
+		if chunk_type == IEND {
+			if !rest.is_empty() {
+				report.push(offset, Problem::TrailingData(rest.len()));
+			}
+			break;
+		}
+	}
+
+	let indexed = report.header.is_some_and(|header| header.color_type == ColorType::Indexed);
+	for (chunk_type, missing) in [(IHDR, true), (PLTE, indexed), (IDAT, true), (IEND, true)] {
+		if missing && !seen.contains(&chunk_type) {
+			report.push(offset, Problem::MissingChunk(chunk_type));
+		}
+	}
+
+	if let (Some(header), Some(idat_offset)) = (report.header, idat_offset) {
+		let problem = decode::image_data(&header, &idat, max_bytes).and_then(|data| match (indexed, palette) {
+			// indices past the end of the palette, which needs the image
+			// expanded to rgba
+			(true, Some(_)) if (header.width as usize).saturating_mul(header.height as usize).saturating_mul(4) > max_bytes => Err(Error::ImageTooLarge),
+			(true, Some(palette)) => decode::expand(&header, &data, Some(palette), transparency).map(|_| ()),
+			_ => Ok(())
+		});
+		match problem {
+			Ok(()) => {},
+			Err(Error::InvalidPng(reason)) => report.push(idat_offset, Problem::InvalidImageData(reason)),
+			Err(_) => report.push(idat_offset, Problem::InvalidImageData("the image is too large to check"))
+		}
+	}
+
+	report
+}
+
+// whether a chunk of `chunk_type` can come after the `seen` chunks
+fn check_order (report: &mut Report, offset: usize, chunk_type: [u8; 4], seen: &[[u8; 4]]) {
+	let has = |other: &[u8; 4]| seen.contains(other);
+	let misplaced = |reason| Problem::MisplacedChunk { chunk_type, reason };
+
+	if UNIQUE.contains(&&chunk_type) && has(&chunk_type) {
+		report.push(offset, Problem::DuplicateChunk(chunk_type));
+	} else if chunk_type == IHDR && !seen.is_empty() {
+		report.push(offset, misplaced("has to be the first chunk"));
+	} else if seen.is_empty() && chunk_type != IHDR {
+		report.push(offset, misplaced("comes before IHDR"));
+	}
+
+	let indexed = report.header.is_some_and(|header| header.color_type == ColorType::Indexed);
+	if chunk_type == IDAT && has(&IDAT) && seen.last() != Some(&IDAT) {
+		report.push(offset, misplaced("has to follow the other IDAT chunks"));
+	} else if [&BEFORE_PLTE[..], &AFTER_PLTE, &BEFORE_IDAT].concat().contains(&&chunk_type) && has(&IDAT) {
+		report.push(offset, misplaced("has to come before the image data"));
+	} else if BEFORE_PLTE.contains(&&chunk_type) && has(&PLTE) {
+		report.push(offset, misplaced("has to come before PLTE"));
+	} else if AFTER_PLTE.contains(&&chunk_type) && indexed && !has(&PLTE) {
+		report.push(offset, misplaced("has to come after PLTE"));
+	}
+}
+
+fn check_palette (report: &mut Report, offset: usize, data: &[u8]) {
+	let invalid = |reason| Problem::InvalidChunk { chunk_type: PLTE, reason };
+
+	if data.is_empty() || !data.len().is_multiple_of(3) || data.len() > 256 * 3 {
+		report.push(offset, invalid("has to hold 1 to 256 rgb entries"));
+	}
+	match report.header {
+		Some(header) if matches!(header.color_type, ColorType::Grayscale | ColorType::GrayscaleAlpha) => {
+			report.push(offset, invalid("isn't allowed for grayscale images"));
+		},
+		Some(header) if header.color_type == ColorType::Indexed && data.len() / 3 > 1 << header.bit_depth => {
+			report.push(offset, invalid("has more entries than the bit depth can index"));
+		},
+		_ => {}
+	}
+}
+
+fn check_transparency (report: &mut Report, offset: usize, data: &[u8], palette: Option<&[u8]>) {
+	let invalid = |reason| Problem::InvalidChunk { chunk_type: *b"tRNS", reason };
+
+	match report.header.map(|header| header.color_type) {
+		Some(ColorType::Grayscale) if data.len() != 2 => report.push(offset, invalid("has to hold a 2 byte gray level")),
+		Some(ColorType::Rgb) if data.len() != 6 => report.push(offset, invalid("has to hold a 6 byte rgb color")),
+		Some(ColorType::Indexed) if data.len() > palette.map_or(0, |palette| palette.len() / 3) => {
+			report.push(offset, invalid("has more entries than the palette"));
+		},
+		Some(ColorType::GrayscaleAlpha | ColorType::Rgba) => report.push(offset, invalid("isn't allowed for images with an alpha channel")),
+		_ => {}
+	}
+}
+
+
+
+#[cfg(test)]
+mod tests {
+
+	use super::{ validate, validate_limited, Diagnostic, Problem, Severity };
+	use crate::{ PngOptions, Color, ColorType, Palette, Text, png };
+
+	#[cfg(not(feature = "std"))]
+	use alloc::{ string::ToString, vec, vec::Vec };
+
+
+
+	// a png of these chunks, with correct crcs
+	fn assemble (chunks: &[(&[u8; 4], &[u8])]) -> Vec<u8> {
+		let mut png = png::HEADER.to_vec();
+		for (chunk_type, data) in chunks {
+			png.extend(png::chunk(*chunk_type, data).unwrap());
+		}
+		png
+	}
+
+	// the chunks of a small valid indexed png
+	fn chunks () -> Vec<(&'static [u8; 4], Vec<u8>)> {
+		let png = PngOptions::new().dimensions(4, 4).format(ColorType::Indexed, 1).generate(235, 35, 35, 255).unwrap();
+		crate::chunks(&png).unwrap()
+			.map(|chunk| chunk.unwrap())
+			.map(|chunk| (*[b"IHDR", b"PLTE", b"IDAT", b"IEND"].iter().find(|&&t| *t == chunk.chunk_type).unwrap(), chunk.data.to_vec()))
+			.collect()
+	}
+
+	// the problems `validate` finds in a png of `chunks`
+	fn problems (chunks: &[(&[u8; 4], Vec<u8>)]) -> Vec<Problem> {
+		let chunks = chunks.iter().map(|(chunk_type, data)| (*chunk_type, &data[..])).collect::<Vec<_>>();
+		validate(&assemble(&chunks)).diagnostics.into_iter().map(|diagnostic| diagnostic.problem).collect()
+	}
+
+	#[test]
+	fn test_valid () {
+		let options = PngOptions::new().dimensions(30, 20).tag_srgb().text(Text::new("Software", "hexpng").unwrap());
+		let pngs = [
+			options.generate(235, 35, 35, 127).unwrap(),
+			options.clone().interlace(true).format(ColorType::Rgb, 16).generate(235, 35, 35, 255).unwrap(),
+			options.clone().idat_size(10).generate_preview(235, 35, 35, 127).unwrap(),
+			options.palette(&Palette::new(&[Color::rgb(235, 35, 35), Color::new(0, 0, 0, 0)])).unwrap(),
+			options.transition(&[Color::rgb(235, 35, 35), Color::rgb(35, 35, 235)], 3, (1, 10)).unwrap()
+		];
+
+		for png in pngs {
+			let report = validate(&png);
+			assert_eq!(report.diagnostics, [], "{}", report);
+			assert!(report.is_valid());
+			assert!(report.header.is_some());
+		}
+
+		let report = validate(&PngOptions::new().generate(235, 35, 35, 255).unwrap());
+		assert!(report.to_string().starts_with("OK: 10x10, "), "{}", report);
+	}
+
+	#[test]
+	fn test_signature_and_crc () {
+		let png = PngOptions::new().generate(235, 35, 35, 255).unwrap();
+		assert_eq!(validate(&png[1..]).diagnostics[0].problem, Problem::BadSignature);
+		assert_eq!(validate(b"").diagnostics[0].problem, Problem::BadSignature);
+
+		let mut corrupt = png.clone();
+		let last = corrupt.len() - 13;
+		corrupt[last] ^= 1;
+		corrupt[last - 8] ^= 1;
+		let report = validate(&corrupt);
+		assert!(matches!(report.diagnostics[0].problem, Problem::CrcMismatch { chunk_type, .. } if chunk_type == *b"IDAT"));
+		assert!(report.to_string().contains("CRC error in chunk IDAT (computed "));
+		assert!(report.to_string().ends_with("ERRORS DETECTED: 2 errors, 0 warnings"), "{}", report);
+	}
+
+	#[test]
+	fn test_structure () {
+		let [ihdr, plte, idat, iend] = <[_; 4]>::try_from(chunks()).unwrap();
+		assert_eq!(problems(&[ihdr.clone(), plte.clone(), idat.clone(), iend.clone()]), []);
+
+		// truncated, so no IEND
+		let png = assemble(&[(ihdr.0, &ihdr.1), (plte.0, &plte.1), (idat.0, &idat.1)]);
+		let report = validate(&png[..png.len() - 2]);
+		assert_eq!(report.diagnostics.iter().map(|d| d.problem).collect::<Vec<_>>(), [Problem::TruncatedChunk, Problem::MissingChunk(*b"IDAT"), Problem::MissingChunk(*b"IEND")]);
+
+		// trailing data is only a warning
+		let png = [assemble(&[(ihdr.0, &ihdr.1), (plte.0, &plte.1), (idat.0, &idat.1), (iend.0, &iend.1)]), b"garbage".to_vec()].concat();
+		let report = validate(&png);
+		assert_eq!(report.diagnostics[0].problem, Problem::TrailingData(7));
+		assert_eq!(report.diagnostics[0].offset, png.len() - 7);
+		assert!(report.is_valid());
+		assert!(report.to_string().ends_with(", 1 warning"), "{}", report);
+
+		// ordering
+		assert_eq!(problems(&[ihdr.clone(), idat.clone(), plte.clone(), iend.clone()]), [
+			Problem::MisplacedChunk { chunk_type: *b"PLTE", reason: "has to come before the image data" }
+		]);
+		assert_eq!(problems(&[plte.clone(), ihdr.clone(), idat.clone(), iend.clone()]), [
+			Problem::MisplacedChunk { chunk_type: *b"PLTE", reason: "comes before IHDR" },
+			Problem::MisplacedChunk { chunk_type: *b"IHDR", reason: "has to be the first chunk" }
+		]);
+		let text = (b"tEXt", b"a\0b".to_vec());
+		assert_eq!(problems(&[ihdr.clone(), plte.clone(), idat.clone(), text.clone(), idat.clone(), iend.clone()]), [
+			Problem::MisplacedChunk { chunk_type: *b"IDAT", reason: "has to follow the other IDAT chunks" }
+		]);
+		assert_eq!(problems(&[ihdr.clone(), plte.clone(), plte.clone(), idat.clone(), iend.clone()]), [Problem::DuplicateChunk(*b"PLTE")]);
+
+		// misplaced ancillary chunks are only warnings
+		let gamma = (b"gAMA", 45455u32.to_be_bytes().to_vec());
+		let report = validate(&assemble(&[(ihdr.0, &ihdr.1), (plte.0, &plte.1), (gamma.0, &gamma.1), (idat.0, &idat.1), (iend.0, &iend.1)]));
+		assert_eq!(report.diagnostics[0].problem, Problem::MisplacedChunk { chunk_type: *b"gAMA", reason: "has to come before PLTE" });
+		assert_eq!(report.diagnostics[0].problem.severity(), Severity::Warning);
+
+		assert_eq!(problems(&[ihdr.clone(), plte.clone(), (b"XYZW", Vec::new()), idat.clone(), iend.clone()]), [Problem::UnknownCriticalChunk(*b"XYZW")]);
+		assert_eq!(problems(&[ihdr.clone(), idat.clone(), iend.clone()]), [Problem::MissingChunk(*b"PLTE")]);
+	}
+
+	#[test]
+	fn test_header () {
+		let [ihdr, plte, idat, iend] = <[_; 4]>::try_from(chunks()).unwrap();
+
+		// 16 bit indexed color
+		let mut header = ihdr.1.clone();
+		header[8] = 16;
+		assert_eq!(problems(&[(b"IHDR", header), plte.clone(), idat.clone(), iend.clone()]), [
+			Problem::InvalidHeader("bit depth isn't allowed for the color type")
+		]);
+
+		let mut header = ihdr.1.clone();
+		header[..4].copy_from_slice(&0u32.to_be_bytes());
+		assert_eq!(problems(&[(b"IHDR", header), plte.clone(), idat.clone(), iend.clone()]), [
+			Problem::InvalidHeader("width and height have to be 1 to 2^31-1")
+		]);
+
+		// a palette in a grayscale image
+		let mut header = ihdr.1.clone();
+		header[9] = 0;
+		assert_eq!(problems(&[(b"IHDR", header), plte.clone(), idat.clone(), iend.clone()]), [
+			Problem::InvalidChunk { chunk_type: *b"PLTE", reason: "isn't allowed for grayscale images" }
+		]);
+	}
+
+	#[test]
+	fn test_image_data () {
+		let [ihdr, plte, idat, iend] = <[_; 4]>::try_from(chunks()).unwrap();
+
+		let mut data = idat.1.clone();
+		data[0] = 0x79;
+		assert_eq!(problems(&[ihdr.clone(), plte.clone(), (b"IDAT", data), iend.clone()]), [
+			Problem::InvalidImageData("the zlib stream isn't deflate with a window of at most 32k")
+		]);
+
+		let mut data = idat.1.clone();
+		let last = data.len() - 1;
+		data[last] ^= 1;
+		assert_eq!(problems(&[ihdr.clone(), plte.clone(), (b"IDAT", data), iend.clone()]), [
+			Problem::InvalidImageData("the zlib stream's adler32 checksum doesn't match")
+		]);
+
+		// a 4x5 image can't fit in the data of a 4x4 one
+		let mut header = ihdr.1.clone();
+		header[7] = 5;
+		assert_eq!(problems(&[(b"IHDR", header), plte.clone(), idat.clone(), iend.clone()]), [
+			Problem::InvalidImageData("the image data is shorter than the image")
+		]);
+	}
+
+	#[test]
+	fn test_limit () {
+		// a few kilobytes of deflate data that decompress to 2 MiB of
+		// scanlines, exactly what a 1023 x 2048 grayscale IHDR needs
+		let idat = crate::deflate::zlib(&vec![0; 2048 * 1024]);
+		let ihdr = [1023u32.to_be_bytes(), 2048u32.to_be_bytes()].concat().into_iter().chain([8, 0, 0, 0, 0]).collect::<Vec<u8>>();
+		let huge = [png::MAX_CHUNK_LEN.to_be_bytes(), png::MAX_CHUNK_LEN.to_be_bytes()].concat().into_iter().chain([8, 0, 0, 0, 0]).collect::<Vec<u8>>();
+
+		let bomb = assemble(&[(b"IHDR", &ihdr), (b"IDAT", &idat), (b"IEND", &[])]);
+		assert!(idat.len() < 1 << 14);
+		assert!(validate(&bomb).is_valid());
+		assert_eq!(validate_limited(&bomb, 1 << 20).diagnostics, [
+			Diagnostic { offset: 33, problem: Problem::InvalidImageData("the image is too large to check") }
+		]);
+
+		// the same data with an IHDR claiming the whole 2^31-1 x 2^31-1
+		let bomb = assemble(&[(b"IHDR", &huge), (b"IDAT", &idat), (b"IEND", &[])]);
+		assert_eq!(validate_limited(&bomb, 1 << 26).diagnostics[0].problem, Problem::InvalidImageData("the image is too large to check"));
+	}
+
+}
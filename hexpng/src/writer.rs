@@ -0,0 +1,345 @@
+// pngs written to a sink a row at a time, instead of built in memory
+
+use crate::{ Color, ColorType, Error, PngOptions, MAX_DIMENSION };
+use crate::{ deflate::ZlibEncoder, filter::RowFilter, format::Format, png };
+
+#[cfg(not(feature = "std"))]
+use alloc::vec::Vec;
+
+
+
+/// somewhere a [`PngWriter`] can write a png to
+///
+/// under std every [`std::io::Write`] is a sink, without std it's
+/// implemented for `Vec<u8>` and can be implemented for anything else
+/// bytes can be sent to, like a serial port
+pub trait Sink {
+	/// write all of `bytes`, or fail
+	fn write_bytes (&mut self, bytes: &[u8]) -> Result<(), Error>;
+}
+
+#[cfg(feature = "std")]
+impl<W: std::io::Write + ?Sized> Sink for W {
+	fn write_bytes (&mut self, bytes: &[u8]) -> Result<(), Error> {
+		self.write_all(bytes).map_err(Error::from)
+	}
+}
+
+#[cfg(not(feature = "std"))]
+impl Sink for Vec<u8> {
+	fn write_bytes (&mut self, bytes: &[u8]) -> Result<(), Error> {
+		self.extend_from_slice(bytes);
+		Ok(())
+	}
+}
+
+#[cfg(not(feature = "std"))]
+impl<S: Sink + ?Sized> Sink for &mut S {
+	fn write_bytes (&mut self, bytes: &[u8]) -> Result<(), Error> {
+		(**self).write_bytes(bytes)
+	}
+}
+
+
+
+/// a png written to a [`Sink`] a row at a time, from [`PngOptions::writer`]
+///
+/// every row is filtered and compressed as soon as it's written, so only
+/// the row before it, a bit of uncompressed data and an IDAT chunk are
+/// held on to however large the image is. the signature and the chunks
+/// before the image data go out with the first row
+///
+/// filters are picked a row at a time, so [`Filter::BruteForce`](crate::Filter::BruteForce)
+/// and [`PngOptions::optimize`] work like [`Filter::Adaptive`](crate::Filter::Adaptive),
+/// and pngs can't be interlaced
+///
+/// ```
+/// use hexpng::{ PngOptions, ColorType };
+///
+/// // a gray gradient, `png` could just as well be a file or a socket
+/// let mut png = Vec::new();
+/// let mut writer = PngOptions::new().dimensions(256, 256).writer(&mut png, ColorType::Grayscale, 8).unwrap();
+/// for y in 0..=255u8 {
+///     let row = (0..=255u8).map(|x| x / 2 + y / 2).collect::<Vec<u8>>();
+///     writer.write_row(&row).unwrap();
+/// }
+/// writer.finish().unwrap();
+/// ```
+pub struct PngWriter<S: Sink> {
+	sink: S,
+	options: PngOptions,
+	format: Format,
+	row_len: usize,
+	filter: RowFilter,
+	// the last row as it was written, and the current one filtered with
+	// its filter byte
+	previous: Vec<u8>,
+	current: Vec<u8>,
+	// rows written so far
+	rows: u32,
+	encoder: ZlibEncoder,
+	// compressed data that doesn't fill an IDAT chunk yet
+	idat: Vec<u8>,
+	idat_len: usize
+}
+
+impl<S: Sink> PngWriter<S> {
+
+	// a writer for `options`' dimensions, storing the pixels with `format`
+	pub(crate) fn new (sink: S, options: &PngOptions, format: Format) -> Result<Self, Error> {
+		let (width, height) = (options.width, options.height);
+		if width == 0 || height == 0 || width > MAX_DIMENSION || height > MAX_DIMENSION {
+			return Err(Error::InvalidDimensions { width, height });
+		}
+		if options.interlace {
+			return Err(Error::UnsupportedOptions("interlaced pngs can't be written a row at a time"));
+		}
+		if !format.color_type.allows_bit_depth(format.bit_depth) {
+			return Err(Error::UnsupportedOptions("bit depth isn't allowed for the color type"));
+		}
+
+		let row_len = format.row_len(width).ok_or(Error::ImageTooLarge)?;
+		let (mut previous, mut current) = (Vec::new(), Vec::new());
+		previous.try_reserve_exact(row_len).map_err(|_| Error::ImageTooLarge)?;
+		current.try_reserve_exact(row_len + 1).map_err(|_| Error::ImageTooLarge)?;
+
+		let bpp = format.color_type.bits_per_pixel(format.bit_depth).div_ceil(8);
+		Ok(Self {
+			sink,
+			options: options.clone(),
+			row_len,
+			filter: RowFilter::new(options.filter, row_len, bpp),
+			format,
+			previous,
+			current,
+			rows: 0,
+			encoder: ZlibEncoder::new(options.compression, options.strategy),
+			idat: Vec::new(),
+			idat_len: options.idat_len()?
+		})
+	}
+
+	/// set the palette of an indexed png, entries that aren't fully opaque
+	/// are stored in a tRNS chunk
+	///
+	/// it has to be set before the first row, which fails with
+	/// [`Error::UnsupportedOptions`] if an indexed png has no palette or
+	/// another color type has one
+	pub fn palette (mut self, palette: &[Color]) -> Self {
+		self.format.palette = palette.iter().map(|c| [c.r, c.g, c.b, c.a]).collect();
+		self
+	}
+
+	/// filter, compress and write the next row of pixels, packed like the
+	/// rows of a [`RawImage`](crate::RawImage)
+	///
+	/// after an error the png written so far is incomplete
+	pub fn write_row (&mut self, row: &[u8]) -> Result<(), Error> {
+		if self.rows == self.options.height {
+			return Err(Error::InvalidImage("more rows than the png's height"));
+		}
+		if row.len() != self.row_len {
+			return Err(Error::InvalidImage("row length doesn't match the png's width"));
+		}
+		if self.rows == 0 {
+			self.format.validate()?;
+		}
+		if self.format.color_type == ColorType::Indexed && !self.format.valid_indices(row, self.row_len, self.row_len, self.options.width) {
+			return Err(Error::InvalidImage("pixel refers to a palette entry that doesn't exist"));
+		}
+		if self.rows == 0 {
+			self.sink.write_bytes(&self.options.header(&self.format)?)?;
+		}
+
+		self.current.clear();
+		self.current.push(0);
+		self.current.extend_from_slice(row);
+		self.filter.apply(&mut self.current, (self.rows > 0).then_some(&self.previous[..]));
+		self.encoder.write(&self.current);
+
+		self.previous.clear();
+		self.previous.extend_from_slice(row);
+		self.rows += 1;
+
+		let compressed = self.encoder.output();
+		self.flush(compressed, false)
+	}
+
+	/// end the png once every row is written, returning the sink
+	pub fn finish (mut self) -> Result<S, Error> {
+		if self.rows < self.options.height {
+			return Err(Error::InvalidImage("fewer rows than the png's height"));
+		}
+
+		let compressed = self.encoder.finish();
+		self.flush(compressed, true)?;
+		self.sink.write_bytes(&png::chunk(b"IEND", b"")?)?;
+
+		Ok(self.sink)
+	}
+
+	// write `compressed` image data as IDAT chunks, only the ones that
+	// are full unless it's the `end` of the image data
+	fn flush (&mut self, compressed: Vec<u8>, end: bool) -> Result<(), Error> {
+		self.idat.extend(compressed);
+
+		let len = match end {
+			true => self.idat.len(),
+			false => self.idat.len() / self.idat_len * self.idat_len
+		};
+		for part in self.idat[..len].chunks(self.idat_len) {
+			self.sink.write_bytes(&png::chunk(b"IDAT", part)?)?;
+		}
+		self.idat.drain(..len);
+
+		Ok(())
+	}
+
+}
+
+
+
+#[cfg(test)]
+mod tests {
+
+	use super::Sink;
+	use crate::{ PngOptions, Color, ColorType, Compression, Error, Filter, RawImage, decode, chunks };
+
+	#[cfg(not(feature = "std"))]
+	use alloc::vec::Vec;
+
+
+
+	// a sink that fails once it's been given `limit` bytes
+	struct Limited {
+		written: usize,
+		limit: usize
+	}
+
+	impl Sink for Limited {
+		fn write_bytes (&mut self, bytes: &[u8]) -> Result<(), Error> {
+			self.written += bytes.len();
+			match self.written > self.limit {
+				true => Err(Error::Io("the sink is full")),
+				false => Ok(())
+			}
+		}
+	}
+
+	// a smooth rgb image with some noise, 3 bytes per pixel
+	fn pixels (width: usize, height: usize) -> Vec<u8> {
+		(0..width * height * 3).map(|i| (i % 251 + i / (width * 3) + i * 7 % 3) as u8).collect()
+	}
+
+	// chunk types in order, with runs of IDAT chunks as one
+	fn chunk_runs (png: &[u8]) -> Vec<[u8; 4]> {
+		let mut types = chunks(png).unwrap().map(|chunk| chunk.unwrap().chunk_type).collect::<Vec<_>>();
+		types.dedup();
+		types
+	}
+
+	#[test]
+	fn test_writer () {
+		let (width, height) = (300, 200);
+		let pixels = pixels(width, height);
+
+		for options in [
+			PngOptions::new(),
+			PngOptions::new().filter(Filter::Paeth).idat_size(1000),
+			PngOptions::new().filter(Filter::BruteForce).compression(Compression::Level(0))
+		] {
+			let options = options.dimensions(width as u32, height as u32);
+			let mut writer = options.writer(Vec::new(), ColorType::Rgb, 8).unwrap();
+			for row in pixels.chunks(width * 3) {
+				writer.write_row(row).unwrap();
+			}
+			let png = writer.finish().unwrap();
+
+			// the same chunks as encoding it all at once, apart from how
+			// the image data is filtered and split into segments
+			let encoded = options.encode(&RawImage::new(&pixels, ColorType::Rgb, 8)).unwrap();
+			assert_eq!(chunk_runs(&png), chunk_runs(&encoded));
+			assert_eq!(decode(&png).unwrap(), decode(&encoded).unwrap());
+		}
+	}
+
+	#[test]
+	fn test_palette () {
+		let palette = [Color::rgb(235, 35, 35), Color::new(0, 0, 0, 0)];
+		let options = PngOptions::new().dimensions(8, 2);
+
+		let mut writer = options.writer(Vec::new(), ColorType::Indexed, 1).unwrap().palette(&palette);
+		writer.write_row(&[0b1010_1010]).unwrap();
+		writer.write_row(&[0b0101_0101]).unwrap();
+		let png = writer.finish().unwrap();
+		let encoded = options.encode(&RawImage::new(&[0b1010_1010, 0b0101_0101], ColorType::Indexed, 1).palette(&palette)).unwrap();
+		assert_eq!(png, encoded);
+
+		// indices past the palette, and no palette at all
+		let mut writer = options.writer(Vec::new(), ColorType::Indexed, 2).unwrap().palette(&palette);
+		assert!(matches!(writer.write_row(&[0b0010_0000, 0]), Err(Error::InvalidImage(_))));
+		let mut writer = options.writer(Vec::new(), ColorType::Indexed, 1).unwrap();
+		assert!(matches!(writer.write_row(&[0]), Err(Error::UnsupportedOptions(_))));
+	}
+
+	#[test]
+	fn test_errors () {
+		let options = PngOptions::new().dimensions(4, 2);
+
+		assert!(matches!(options.writer(Vec::new(), ColorType::Rgb, 4), Err(Error::UnsupportedOptions(_))));
+		assert!(matches!(options.clone().interlace(true).writer(Vec::new(), ColorType::Rgb, 8), Err(Error::UnsupportedOptions(_))));
+		assert!(matches!(options.clone().width(0).writer(Vec::new(), ColorType::Rgb, 8), Err(Error::InvalidDimensions { .. })));
+		assert!(matches!(options.clone().idat_size(0).writer(Vec::new(), ColorType::Rgb, 8), Err(Error::UnsupportedOptions(_))));
+
+		let mut writer = options.writer(Vec::new(), ColorType::Grayscale, 8).unwrap();
+		assert!(matches!(writer.write_row(&[0; 5]), Err(Error::InvalidImage(_))));
+		writer.write_row(&[0; 4]).unwrap();
+		let mut unfinished = options.writer(Vec::new(), ColorType::Grayscale, 8).unwrap();
+		unfinished.write_row(&[0; 4]).unwrap();
+		assert!(matches!(unfinished.finish(), Err(Error::InvalidImage(_))));
+		writer.write_row(&[0; 4]).unwrap();
+		assert!(matches!(writer.write_row(&[0; 4]), Err(Error::InvalidImage(_))));
+		assert!(writer.finish().is_ok());
+
+		// the sink's errors are passed on
+		let mut writer = options.writer(Limited { written: 0, limit: 20 }, ColorType::Grayscale, 8).unwrap();
+		assert_eq!(writer.write_row(&[0; 4]), Err(Error::Io("the sink is full")));
+	}
+
+	#[cfg(feature = "std")]
+	#[test]
+	fn test_io_errors () {
+		use std::io::ErrorKind;
+
+		// a slice that fills up keeps the kind of error
+		let mut buffer = [0; 10];
+		let result = PngOptions::new().generate_to(&mut buffer[..], 235, 35, 35, 255);
+		assert_eq!(result, Err(Error::StdIo { kind: ErrorKind::WriteZero, os_error: None }));
+
+		// and os errors their code
+		let error = Error::from(std::io::Error::from_raw_os_error(2));
+		assert_eq!(error, Error::StdIo { kind: ErrorKind::NotFound, os_error: Some(2) });
+		assert!(error.to_string().ends_with("(os error 2)"));
+	}
+
+	#[test]
+	fn test_generate_to () {
+		let options = PngOptions::new().dimensions(100, 30);
+		for [r, g, b, a] in [[235, 35, 35, 255], [35, 35, 35, 255], [235, 35, 35, 127], [0, 0, 0, 0]] {
+			for options in [options.clone(), options.clone().format(ColorType::Rgba, 16)] {
+				let png = options.generate_to(Vec::new(), r, g, b, a).unwrap();
+				assert_eq!(decode(&png).unwrap().pixels, [r, g, b, a].repeat(100 * 30));
+			}
+		}
+
+		// without a format, the one with the fewest bits per pixel, as
+		// (bit depth, color type)
+		assert_eq!(options.generate_to(Vec::new(), 235, 35, 35, 255).unwrap()[24..26], [1, 3]);
+		assert_eq!(options.generate_to(Vec::new(), 0, 0, 0, 0).unwrap()[24..26], [1, 0]);
+
+		// a label can't be drawn a row at a time, so it's the same png
+		let options = options.label(crate::Label::hex(Color::rgb(235, 35, 35)));
+		assert_eq!(options.generate_to(Vec::new(), 235, 35, 35, 255).unwrap(), options.generate(235, 35, 35, 255).unwrap());
+	}
+
+}
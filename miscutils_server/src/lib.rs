@@ -1,6 +1,9 @@
 
 
 
+// doc examples are indented with tabs like the rest of the code
+#![allow(clippy::tabs_in_doc_comments)]
+
 #[macro_use]
 extern crate log;
 
@@ -33,6 +36,7 @@ impl ThreadPool {
 	/// - the `new` function will panic if the size is zero
 	/// 
 	/// ```
+	/// # use miscutils_server::ThreadPool;
 	/// let pool = ThreadPool::new(4);
 	/// ```
 	/// 
@@ -60,11 +64,12 @@ impl ThreadPool {
 	/// which will be executed when there's a worker available
 	/// 
 	/// ```
+	/// # use miscutils_server::ThreadPool;
 	/// let pool = ThreadPool::new(3); // 3 threads
 	/// for job_id in 0..8 {           // 8 jobs
-	/// 	pool.execute(|| {
+	/// 	pool.execute(move || {
 	/// 		for i in 0..5 {
-	/// 			std::thread::sleep(0.5);
+	/// 			std::thread::sleep(std::time::Duration::from_millis(500));
 	/// 			println!("job {:?}: {:?}", job_id, i);
 	/// 		}
 	/// 	});
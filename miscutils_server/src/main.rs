@@ -7,7 +7,6 @@ use rand::{ Rng, thread_rng };                     // random numbers
 
 #[macro_use]
 extern crate log;                                  // logger
-use simple_logger;
 
 use std::{
 	io::{ BufReader, prelude::* },                 // for writing responses to eastablished tcp connections
@@ -15,18 +14,52 @@ use std::{
 	process::exit                                  // instantly exit the program
 };
 
-use hexpng::generate_png;                          // generate png from hex code
+use hexpng::{ PngOptions, Color, Compression, Error, Identicon, Text, validate_limited };    // generate png from hex code
 
 use miscutils_server::ThreadPool;
 
 
 
+// the largest request body the server will read
+const MAX_BODY: usize = 16 << 20;
+// the most a checked png's image data may decompress to, a small deflate
+// stream can claim far more than the server has memory for
+const MAX_DECODED: usize = 64 << 20;
+
+
+
+// skip the headers of a request, then read as many bytes of body as its
+// Content-Length says
+fn read_body (reader: &mut impl BufRead) -> Option<Vec<u8>> {
+
+	let mut length = None;
+	for line in reader.by_ref().lines() {
+		let line = line.ok()?;
+		if line.is_empty() {
+			break;
+		}
+		if let Some((name, value)) = line.split_once(':') {
+			if name.trim().eq_ignore_ascii_case("content-length") {
+				length = value.trim().parse::<usize>().ok();
+			}
+		}
+	}
+
+	let mut body = vec![0; length.filter(|&length| length <= MAX_BODY)?];
+	reader.read_exact(&mut body).ok()?;
+	Some(body)
+
+}
+
+
+
 fn handle_connection (mut stream: TcpStream) {
 
-	let buffer_reader = BufReader::new(&mut stream);
+	let (request_line, body) = {
+		let mut buffer_reader = BufReader::new(&mut stream);
 
-	let request_line = {
-		buffer_reader
+		let request_line = buffer_reader
+			.by_ref()
 			.lines()
 			.next().unwrap_or_else(|| {
 				warn!("empty request ¯\\_(ツ)_/¯");
@@ -36,43 +69,103 @@ fn handle_connection (mut stream: TcpStream) {
 				warn!("{:?}", e);
 				warn!("error while reading tcp request");
 				"".to_string()
-			})
+			});
+
+		// only pngcheck takes a body, don't wait on one for anything else
+		let body = request_line.starts_with("POST /pngcheck ").then(|| read_body(&mut buffer_reader)).flatten();
+
+		(request_line, body)
 	};
 
 
 
 	let mut status = "HTTP/1.0 400 NOT FOUND";
 	let mut bytes: Vec<u8> = include_bytes!("html/404.html").to_vec();
+	let mut content_type = "text/html";
+
+	// request line looks like `GET /path HTTP/1.1`
+	let mut request = request_line.split_whitespace();
+	let method = request.next().unwrap_or("");
+	let path = request.next().unwrap_or("");
 
-	if request_line.contains("GET / HTTP") {
+	if method == "GET" && path == "/" {
 		status = "HTTP/1.0 200 OK";
 		bytes = include_bytes!("html/index.html").to_vec();
-	} else if request_line.contains("GET /hexpng/") {
-		let col = {
-			let s = &request_line[12..request_line.len()-9];
-			if s.len()%2==0 {
-				(0..s.len())
-					.step_by(2)
-					.map(|i| u8::from_str_radix(&s[i..i + 2], 16).unwrap())
-					.collect::<Vec<u8>>()
-			} else { vec![] }
-		};
-
-		if col.len() == 3 {
-			status = "HTTP/1.0 200 OK";
-			bytes = generate_png(col[0], col[1], col[2], 255);
-		} else if col.len() == 4 {
-			status = "HTTP/1.0 200 OK";
-			bytes = generate_png(col[0], col[1], col[2], col[3]);
+	} else if let Some(hexcode) = path.strip_prefix("/hexpng/").filter(|_| method == "GET") {
+		// the swatch is tiny, so it's built in memory, where optimizing
+		// can try every format and a failure can still be a 500
+		let png = hexcode.parse::<Color>().map_err(Error::from).and_then(|c| {
+			// tag the swatch with where it came from, and squeeze every
+			// byte out of it since it's tiny anyway
+			PngOptions::new()
+				.compression(Compression::Max)
+				.optimize(true)
+				.text(Text::new("Software", "miscutils_server")?)
+				.text(Text::new("Source", hexcode)?)
+				.generate(c.r, c.g, c.b, c.a)
+		});
+
+		match png {
+			Ok(png) => {
+				status = "HTTP/1.0 200 OK";
+				content_type = "image/png";
+				bytes = png;
+			},
+			Err(e @ Error::InvalidColor(_)) => {
+				warn!("{}", e);
+			},
+			Err(e) => {
+				warn!("{}", e);
+				warn!("error while generating png");
+				status = "HTTP/1.0 500 INTERNAL SERVER ERROR";
+				bytes = vec![];
+			}
+		}
+	} else if method == "POST" && path == "/pngcheck" {
+		// a pngcheck-like report of everything wrong with the posted png
+		match body {
+			Some(png) => {
+				status = "HTTP/1.0 200 OK";
+				content_type = "text/plain";
+				bytes = format!("{}\n", validate_limited(&png, MAX_DECODED)).into_bytes();
+			},
+			None => {
+				warn!("couldn't read the png to check");
+				status = "HTTP/1.0 400 BAD REQUEST";
+				content_type = "text/plain";
+				bytes = format!("post a png of at most {} bytes with a Content-Length\n", MAX_BODY).into_bytes();
+			}
+		}
+	} else if let Some(input) = path.strip_prefix("/identicon/").filter(|_| method == "GET") {
+		// the same path always gives the same avatar
+		let png = Text::new("Software", "miscutils_server").and_then(|software| {
+			PngOptions::new()
+				.dimensions(240, 240)
+				.text(software)
+				.identicon(&Identicon::new(input))
+		});
+
+		match png {
+			Ok(png) => {
+				status = "HTTP/1.0 200 OK";
+				content_type = "image/png";
+				bytes = png;
+			},
+			Err(e) => {
+				warn!("{}", e);
+				warn!("error while generating identicon");
+				status = "HTTP/1.0 500 INTERNAL SERVER ERROR";
+				bytes = vec![];
+			}
 		}
 	}
 
 
 
 	let length = bytes.len();
-	let response = format!("{status}\r\nContent-Length: {length}\r\n\r\n");
+	let response = format!("{status}\r\nContent-Type: {content_type}\r\nContent-Length: {length}\r\n\r\n");
 	let response = response.bytes();
-	let response = response.chain(bytes.into_iter()).collect::<Vec<u8>>();
+	let response = response.chain(bytes).collect::<Vec<u8>>();
 
 	stream.write_all(&response[..]).unwrap_or_else(|e| {
 		warn!("{:?}", e);